@@ -0,0 +1,322 @@
+use std::env;
+
+use unicode_width::UnicodeWidthStr;
+use wordfreq::{RankedWord, WordCounter};
+
+use crate::{format_number, Config};
+
+// Eighths of a block, so bars grow in steps finer than a column.
+const BLOCKS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+const ANSI_COLORS: [&str; 6] = [
+    "\x1b[36m", "\x1b[32m", "\x1b[33m", "\x1b[35m", "\x1b[34m", "\x1b[31m",
+];
+const ANSI_RESET: &str = "\x1b[0m";
+
+const SVG_COLORS: [&str; 6] = [
+    "#1f77b4", "#2ca02c", "#ff7f0e", "#9467bd", "#17becf", "#d62728",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SvgStyle {
+    Bars,
+    Cloud,
+}
+
+impl SvgStyle {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "bars" => Some(SvgStyle::Bars),
+            "cloud" => Some(SvgStyle::Cloud),
+            _ => None,
+        }
+    }
+}
+
+// The ranked words a chart shows, with the label printed after each bar.
+fn rows<'a>(counter: &'a WordCounter, config: &Config) -> Vec<(RankedWord<'a>, String)> {
+    let total = counter.total();
+    counter
+        .ranked()
+        .take(config.top)
+        .map(|entry| {
+            let mut label = match counter.error(entry.word) {
+                0 => format_number(entry.count),
+                _ => format!("~{}", format_number(entry.count)),
+            };
+            if config.percent {
+                let percent = 100.0 * entry.count as f64 / total.max(1) as f64;
+                label += &format!(" ({:.1}%)", percent);
+            }
+            (entry, label)
+        })
+        .collect()
+}
+
+// How much of the longest bar a count fills, between 0 and 1.
+fn scale(count: usize, max: usize, log_scale: bool) -> f64 {
+    if max == 0 {
+        0.0
+    } else if log_scale {
+        (1.0 + count as f64).ln() / (1.0 + max as f64).ln()
+    } else {
+        count as f64 / max as f64
+    }
+}
+
+// Terminal cells, so wide CJK characters and emoji count twice.
+fn display_width(s: &str) -> usize {
+    s.width()
+}
+
+// Prints one bar per ranked word, scaled so the longest line fits the
+// terminal.
+pub fn print_bars(counter: &WordCounter, config: &Config) {
+    let rows = rows(counter, config);
+    let Some(max) = rows.first().map(|(entry, _)| entry.count) else {
+        return;
+    };
+
+    let word_width = rows
+        .iter()
+        .map(|(entry, _)| display_width(entry.word))
+        .max()
+        .unwrap_or(0);
+    let label_width = rows.iter().map(|(_, label)| label.len()).max().unwrap_or(0);
+    let bar_width = terminal_width()
+        .saturating_sub(word_width + label_width + 2)
+        .max(10);
+
+    for (index, (entry, label)) in rows.iter().enumerate() {
+        let padding = " ".repeat(word_width - display_width(entry.word));
+        let bar = bar(scale(entry.count, max, config.log_scale) * bar_width as f64);
+        if config.color {
+            let color = ANSI_COLORS[index % ANSI_COLORS.len()];
+            println!(
+                "{}{} {}{}{} {}",
+                entry.word, padding, color, bar, ANSI_RESET, label
+            );
+        } else {
+            println!("{}{} {} {}", entry.word, padding, bar, label);
+        }
+    }
+}
+
+fn bar(columns: f64) -> String {
+    let eighths = (columns * 8.0).round() as usize;
+    let (full, rest) = (eighths / 8, eighths % 8);
+    let mut bar = "█".repeat(full);
+    if rest > 0 {
+        bar.push(BLOCKS[rest - 1]);
+    }
+    // Every counted word gets at least a sliver.
+    if bar.is_empty() {
+        bar.push(BLOCKS[0]);
+    }
+    bar
+}
+
+// The width of the terminal the chart is printed to, else $COLUMNS, else
+// 80.
+fn terminal_width() -> usize {
+    terminal_columns()
+        .or_else(|| env::var("COLUMNS").ok()?.parse().ok())
+        .filter(|&columns| columns > 0)
+        .unwrap_or(80)
+}
+
+#[cfg(unix)]
+fn terminal_columns() -> Option<usize> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // Fails unless stdout is a terminal.
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    (result == 0 && size.ws_col > 0).then_some(size.ws_col as usize)
+}
+
+#[cfg(not(unix))]
+fn terminal_columns() -> Option<usize> {
+    None
+}
+
+pub fn svg(counter: &WordCounter, config: &Config) -> String {
+    match config.svg_style {
+        SvgStyle::Bars => svg_bars(counter, config),
+        SvgStyle::Cloud => svg_cloud(counter, config),
+    }
+}
+
+const BAR_HEIGHT: f64 = 20.0;
+const BAR_GAP: f64 = 6.0;
+const CHART_WIDTH: f64 = 800.0;
+const FONT_SIZE: f64 = 14.0;
+
+// Text is measured with an average glyph width, close enough for the
+// proportional sans-serif fonts SVG viewers fall back to.
+fn text_width(text: &str, font_size: f64) -> f64 {
+    display_width(text) as f64 * font_size * 0.6
+}
+
+fn svg_bars(counter: &WordCounter, config: &Config) -> String {
+    let rows = rows(counter, config);
+    let max = rows.first().map_or(0, |(entry, _)| entry.count);
+
+    let word_width = rows
+        .iter()
+        .map(|(entry, _)| text_width(entry.word, FONT_SIZE))
+        .fold(0.0, f64::max);
+    let label_width = rows
+        .iter()
+        .map(|(_, label)| text_width(label, FONT_SIZE))
+        .fold(0.0, f64::max);
+    let bar_x = word_width + 2.0 * BAR_GAP;
+    let bar_width = (CHART_WIDTH - bar_x - label_width - 2.0 * BAR_GAP).max(100.0);
+    let height = rows.len() as f64 * (BAR_HEIGHT + BAR_GAP) + BAR_GAP;
+
+    let mut out = svg_header(CHART_WIDTH, height);
+    for (index, (entry, label)) in rows.iter().enumerate() {
+        let y = BAR_GAP + index as f64 * (BAR_HEIGHT + BAR_GAP);
+        let text_y = y + BAR_HEIGHT * 0.75;
+        let width = (scale(entry.count, max, config.log_scale) * bar_width).max(1.0);
+        out += &format!(
+            "  <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\n",
+            word_width + BAR_GAP,
+            text_y,
+            xml_escape(entry.word)
+        );
+        out += &format!(
+            "  <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>\n",
+            bar_x,
+            y,
+            width,
+            BAR_HEIGHT,
+            SVG_COLORS[index % SVG_COLORS.len()]
+        );
+        out += &format!(
+            "  <text x=\"{:.1}\" y=\"{:.1}\">{}</text>\n",
+            bar_x + width + BAR_GAP,
+            text_y,
+            xml_escape(label)
+        );
+    }
+    out + "</svg>\n"
+}
+
+const CLOUD_WIDTH: f64 = 800.0;
+const CLOUD_HEIGHT: f64 = 600.0;
+const MIN_FONT: f64 = 12.0;
+const MAX_FONT: f64 = 72.0;
+
+// Places the words largest first along a spiral out from the centre,
+// each at the first spot where it overlaps no placed word. Words that
+// find no spot on the canvas are left out.
+fn svg_cloud(counter: &WordCounter, config: &Config) -> String {
+    let rows = rows(counter, config);
+    let max = rows.first().map_or(0, |(entry, _)| entry.count);
+    let mut placed: Vec<(f64, f64, f64, f64)> = Vec::new();
+
+    let mut out = svg_header(CLOUD_WIDTH, CLOUD_HEIGHT);
+    for (index, (entry, _)) in rows.iter().enumerate() {
+        let size = MIN_FONT + (MAX_FONT - MIN_FONT) * scale(entry.count, max, config.log_scale);
+        let (width, height) = (text_width(entry.word, size), size);
+
+        let spot = (0..5000).map(|step| step as f64 * 0.1).find_map(|angle| {
+            let x = CLOUD_WIDTH / 2.0 + 4.0 * angle * angle.cos() - width / 2.0;
+            let y = CLOUD_HEIGHT / 2.0 + 3.0 * angle * angle.sin() - height / 2.0;
+            let inside =
+                x >= 0.0 && y >= 0.0 && x + width <= CLOUD_WIDTH && y + height <= CLOUD_HEIGHT;
+            let free = placed.iter().all(|&(px, py, pw, ph)| {
+                x + width <= px || px + pw <= x || y + height <= py || py + ph <= y
+            });
+            (inside && free).then_some((x, y))
+        });
+        let Some((x, y)) = spot else {
+            continue;
+        };
+        placed.push((x, y, width, height));
+
+        out += &format!(
+            "  <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"{:.1}\" fill=\"{}\">{}</text>\n",
+            x,
+            y + height * 0.8,
+            size,
+            SVG_COLORS[index % SVG_COLORS.len()],
+            xml_escape(entry.word)
+        );
+    }
+    out + "</svg>\n"
+}
+
+fn svg_header(width: f64, height: f64) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.0}\" height=\"{h:.0}\" \
+         viewBox=\"0 0 {w:.0} {h:.0}\" font-family=\"sans-serif\" font-size=\"{f}\">\n  \
+         <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n",
+        w = width,
+        h = height,
+        f = FONT_SIZE
+    )
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c if c.is_control() => out.push('\u{FFFD}'),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bars_grow_in_eighths_with_a_sliver_at_least() {
+        assert_eq!(bar(3.0), "███");
+        assert_eq!(bar(1.5), "█▌");
+        assert_eq!(bar(0.125), "▏");
+        assert_eq!(bar(0.0), "▏");
+        assert_eq!(bar(2.99), "███");
+    }
+
+    #[test]
+    fn scale_is_linear_or_logarithmic() {
+        assert_eq!(scale(5, 10, false), 0.5);
+        assert_eq!(scale(10, 10, true), 1.0);
+        assert_eq!(scale(0, 10, true), 0.0);
+        // A count of 1 against 1000 stays visible on a log scale.
+        assert!(scale(1, 1000, true) > 0.1);
+        assert!(scale(1, 1000, false) < 0.01);
+        assert_eq!(scale(3, 0, false), 0.0);
+    }
+
+    #[test]
+    fn wide_words_take_two_cells_per_character() {
+        assert_eq!(display_width("word"), 4);
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("👍"), 2);
+        assert_eq!(display_width("cafe\u{301}"), 4);
+        assert_eq!(text_width("日本", 10.0), text_width("abcd", 10.0));
+    }
+
+    #[test]
+    fn xml_escape_leaves_valid_text() {
+        assert_eq!(
+            xml_escape("<a href=\"x\">R&D</a>"),
+            "&lt;a href=&quot;x&quot;&gt;R&amp;D&lt;/a&gt;"
+        );
+        assert_eq!(xml_escape("tab\there"), "tab\u{FFFD}here");
+        assert_eq!(xml_escape("café"), "café");
+    }
+}
@@ -0,0 +1,999 @@
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::process;
+use std::str::FromStr;
+
+/// A command-line option, written `--long VALUE` or `--long=VALUE` when it
+/// takes a value.
+pub struct Flag {
+    pub long: &'static str,
+    pub short: Option<char>,
+    /// Placeholder for the value in help, or `None` for a switch.
+    pub value: Option<&'static str>,
+    /// Values offered by shell completion, also listed when a value is
+    /// rejected.
+    pub choices: &'static [&'static str],
+    pub repeatable: bool,
+    pub help: &'static str,
+    /// Further lines of help, shown indented below the first.
+    pub details: &'static [&'static str],
+}
+
+impl Flag {
+    const fn switch(long: &'static str, help: &'static str) -> Self {
+        Self {
+            long,
+            short: None,
+            value: None,
+            choices: &[],
+            repeatable: false,
+            help,
+            details: &[],
+        }
+    }
+
+    const fn value(long: &'static str, value: &'static str, help: &'static str) -> Self {
+        Self {
+            value: Some(value),
+            ..Self::switch(long, help)
+        }
+    }
+
+    const fn short(self, short: char) -> Self {
+        Self {
+            short: Some(short),
+            ..self
+        }
+    }
+
+    const fn choices(self, choices: &'static [&'static str]) -> Self {
+        Self { choices, ..self }
+    }
+
+    const fn repeatable(self) -> Self {
+        Self {
+            repeatable: true,
+            ..self
+        }
+    }
+
+    const fn details(self, details: &'static [&'static str]) -> Self {
+        Self { details, ..self }
+    }
+
+    // Values naming a file are completed with file names.
+    fn takes_file(&self) -> bool {
+        matches!(self.value, Some("FILE" | "LIST"))
+    }
+
+    fn names(&self) -> String {
+        match self.short {
+            Some(short) => format!("-{}, --{}", short, self.long),
+            None => format!("--{}", self.long),
+        }
+    }
+}
+
+// Options that do something instead of setting up a count; they cannot
+// be given defaults, and a run using them reads no defaults.
+const ACTIONS: &[&str] = &["completions", "man", "no-config", "version", "help"];
+
+pub const FLAGS: &[Flag] = &[
+    Flag::value("top", "N", "Show top N words [default: 10]"),
+    Flag::value("min-length", "N", "Ignore words shorter than N [default: 1]"),
+    Flag::switch("ignore-case", "Case insensitive counting"),
+    Flag::switch(
+        "case-fold",
+        "Case insensitive counting with full Unicode case folding (ß = ss)",
+    ),
+    Flag::value(
+        "normalize",
+        "FORM",
+        "Unicode normalization before counting: nfc, nfkc",
+    )
+    .choices(&["nfc", "nfkc"]),
+    Flag::switch(
+        "strip-diacritics",
+        "Remove accents and other combining marks (é = e)",
+    ),
+    Flag::value(
+        "tokenizer",
+        "NAME",
+        "Word splitting: simple, unicode (UAX #29) [default: simple]",
+    )
+    .choices(&["simple", "unicode"])
+    .details(&["(with unicode, --min-length counts grapheme clusters instead of bytes)"]),
+    Flag::value(
+        "token-pattern",
+        "REGEX",
+        "Words are the matches of REGEX, e.g. '#\\w+' or '[A-Za-z_]\\w*'",
+    )
+    .details(&["(matched against the normalized text; a match cannot span lines)"]),
+    Flag::value(
+        "include",
+        "REGEX",
+        "Only count words matching REGEX (repeatable, any may match)",
+    )
+    .repeatable(),
+    Flag::value("exclude", "REGEX", "Skip words matching REGEX (repeatable)").repeatable(),
+    Flag::value(
+        "stopwords",
+        "LIST",
+        "Drop stop words: a file (one per line, # comments) or one of de, en, es, fr, it, nl, pt (repeatable)",
+    )
+    .choices(&["de", "en", "es", "fr", "it", "nl", "pt"])
+    .repeatable()
+    .details(&["(a file named like a bundled list is given as a path, e.g. ./en)"]),
+    Flag::value(
+        "stem",
+        "LANG",
+        "Count words by their stem: en, de, fr, es, it, pt, nl, da, fi, no, ru, sv",
+    )
+    .choices(&[
+        "en", "de", "fr", "es", "it", "pt", "nl", "da", "fi", "no", "ru", "sv",
+    ])
+    .details(&["(stems are lowercase; combine with --ignore-case)"]),
+    Flag::switch(
+        "surface-forms",
+        "With --stem, show the most frequent form of each stem",
+    ),
+    Flag::value(
+        "ngram",
+        "N",
+        "Count sequences of N adjacent words instead of single words [default: 1]",
+    ),
+    Flag::switch(
+        "sentence-boundaries",
+        "Do not form n-grams across . ! ? and similar",
+    ),
+    Flag::value(
+        "unit",
+        "UNIT",
+        "What to count: word, char, grapheme, byte [default: word]",
+    )
+    .choices(&["word", "char", "grapheme", "byte"])
+    .details(&["(bytes are shown as hex, invisible characters as U+XXXX)"]),
+    Flag::value(
+        "encoding",
+        "ENCODING",
+        "Encoding of the input, e.g. utf-8, utf-16le, latin1, shift_jis [default: auto]",
+    )
+    .details(&["(auto honours byte order marks and tells UTF-16 and Latin-1 from UTF-8)"]),
+    Flag::switch(
+        "lossy",
+        "Replace invalid text with U+FFFD instead of failing, and report how much",
+    ),
+    Flag::switch(
+        "text",
+        "Treat arguments as the text to analyze instead of paths",
+    ),
+    Flag::value(
+        "json-path",
+        "PATH",
+        "Only count the strings at PATH of each JSON document (repeatable)",
+    )
+    .repeatable()
+    .details(&["(keys joined by dots, * for every key or element, . for the whole document)"]),
+    Flag::value(
+        "csv-column",
+        "COLUMN",
+        "Only count the CSV column named COLUMN, or numbered from 1 (repeatable)",
+    )
+    .repeatable(),
+    Flag::value(
+        "csv-delimiter",
+        "CHAR",
+        "Field delimiter for --csv-column, or tab [default: ,]",
+    ),
+    Flag::switch(
+        "csv-no-header",
+        "The CSV input has no header row; columns are given by number",
+    ),
+    Flag::switch(
+        "html",
+        "Count the text of HTML input, without tags, scripts and entities",
+    ),
+    Flag::switch(
+        "markdown",
+        "Count the text of Markdown input, without its syntax",
+    ),
+    Flag::value(
+        "include-files",
+        "GLOB",
+        "Only count files in directories matching GLOB (repeatable)",
+    )
+    .repeatable(),
+    Flag::value(
+        "exclude-files",
+        "GLOB",
+        "Skip files and directories matching GLOB (repeatable)",
+    )
+    .repeatable(),
+    Flag::value("jobs", "N", "Count with N worker threads [default: 1]").short('j'),
+    Flag::value(
+        "format",
+        "FORMAT",
+        "Output format: text, json, csv, tsv [default: text]",
+    )
+    .choices(&["text", "json", "csv", "tsv"])
+    .details(&["(CSV and TSV totals are written to stderr as # lines; --stats follows as a second table)"]),
+    Flag::switch(
+        "compare",
+        "Compare two inputs (files, directories or texts) word by word",
+    ),
+    Flag::value(
+        "compare-by",
+        "MEASURE",
+        "Rank --compare by: change, relative, ll, chi2 [default: change]",
+    )
+    .choices(&["change", "relative", "ll", "chi2"]),
+    Flag::switch(
+        "code",
+        "Count identifiers, keywords, comments and strings of source code separately",
+    ),
+    Flag::value(
+        "code-language",
+        "LANG",
+        "Lexer for --code: rust, python, javascript, c, java, go",
+    )
+    .choices(&["rust", "python", "javascript", "c", "java", "go"])
+    .details(&["[default: from the file extension; files of other languages are skipped]"]),
+    Flag::switch(
+        "split-identifiers",
+        "Count the parts of camelCase and snake_case identifiers",
+    ),
+    Flag::switch(
+        "tfidf",
+        "Rank terms by TF-IDF per document and over all documents",
+    ),
+    Flag::value(
+        "documents",
+        "KIND",
+        "What a document is for --tfidf: files, lines, paragraphs [default: files]",
+    )
+    .choices(&["files", "lines", "paragraphs"])
+    .details(&["(lines and paragraphs are labeled with their first line number)"]),
+    Flag::value(
+        "kwic",
+        "WORD",
+        "Show every occurrence of WORD in its context (repeatable)",
+    )
+    .repeatable(),
+    Flag::value(
+        "kwic-top",
+        "N",
+        "Show the occurrences of the N most frequent words",
+    ),
+    Flag::value(
+        "context",
+        "N",
+        "Characters of context on each side of an occurrence [default: 30]",
+    ),
+    Flag::switch(
+        "collocations",
+        "Rank pairs of words that occur close together more often than chance",
+    ),
+    Flag::value(
+        "collocations-by",
+        "MEASURE",
+        "Rank --collocations by: pmi, t-score, ll [default: ll]",
+    )
+    .choices(&["pmi", "t-score", "ll"]),
+    Flag::value(
+        "span",
+        "N",
+        "Pair each word with the next N counted words [default: 1]",
+    ),
+    Flag::value(
+        "min-pair-count",
+        "N",
+        "Only rank pairs seen at least N times [default: 3]",
+    ),
+    Flag::value(
+        "min-word-count",
+        "N",
+        "Only rank pairs of words each seen at least N times [default: 1]",
+    ),
+    Flag::value(
+        "load",
+        "FILE",
+        "Add counts saved by --save (repeatable; stdin is not read unless given as -)",
+    )
+    .repeatable(),
+    Flag::value("save", "FILE", "Save the merged counts to FILE for a later --load")
+        .details(&["(saved counts can only be loaded with the same counting options)"]),
+    Flag::value(
+        "approximate",
+        "N",
+        "Count in bounded memory, tracking at most N distinct words",
+    )
+    .details(&["(counts may be too high; the lowest possible count is shown next to them)"]),
+    Flag::switch(
+        "stats",
+        "Add corpus statistics: type/token ratio, hapax legomena, average word",
+    )
+    .details(&["length, sentences and a Zipf fit (log count against log rank)"]),
+    Flag::switch(
+        "follow",
+        "Keep reading a growing file (or stdin) and redraw the ranking periodically",
+    ),
+    Flag::value(
+        "interval",
+        "SECONDS",
+        "Seconds between redraws with --follow [default: 2]",
+    ),
+    Flag::value(
+        "window-lines",
+        "N",
+        "Rank only the last N lines with --follow",
+    ),
+    Flag::value(
+        "window-seconds",
+        "N",
+        "Rank only the lines read in the last N seconds with --follow",
+    ),
+    Flag::switch(
+        "chart",
+        "Draw the ranking as a bar chart fitted to the terminal width",
+    ),
+    Flag::value(
+        "color",
+        "WHEN",
+        "Colour chart bars and --kwic words: auto, always, never [default: auto]",
+    )
+    .choices(&["auto", "always", "never"]),
+    Flag::switch(
+        "log-scale",
+        "Scale bars logarithmically, for rankings with a few huge counts",
+    ),
+    Flag::switch(
+        "percent",
+        "Label bars with each word's share of all tokens",
+    ),
+    Flag::value("svg", "FILE", "Also write the ranking to FILE as an SVG image"),
+    Flag::value(
+        "svg-style",
+        "STYLE",
+        "Image to draw: bars, cloud [default: bars]",
+    )
+    .choices(&["bars", "cloud"]),
+    Flag::switch(
+        "no-header",
+        "Omit the CSV/TSV column header and the # lines on stderr",
+    ),
+    Flag::switch(
+        "no-config",
+        "Ignore the config file and WORDFREQ_* environment variables",
+    ),
+    Flag::value(
+        "completions",
+        "SHELL",
+        "Print a completion script for bash, zsh or fish",
+    )
+    .choices(&["bash", "zsh", "fish"]),
+    Flag::switch("man", "Print the manual page (roff)"),
+    Flag::switch("version", "Print the version").short('V'),
+    Flag::switch("help", "Print help").short('h'),
+];
+
+fn find(long: &str) -> Option<&'static Flag> {
+    FLAGS.iter().find(|flag| flag.long == long)
+}
+
+pub enum Arg {
+    /// An option and its value, empty for a switch.
+    Flag(Given, String),
+    Input(String),
+}
+
+/// An option together with where it was given, so errors about its value
+/// can point there.
+pub struct Given {
+    flag: &'static Flag,
+    origin: Origin,
+}
+
+enum Origin {
+    CommandLine,
+    Environment(String),
+    ConfigFile(String, usize),
+}
+
+impl Given {
+    fn new(flag: &'static Flag, origin: Origin) -> Self {
+        Self { flag, origin }
+    }
+
+    /// The option as errors name it: `--top`, `WORDFREQ_TOP`, or `--top`
+    /// with the config file line.
+    pub fn name(&self) -> String {
+        match &self.origin {
+            Origin::CommandLine => format!("--{}", self.flag.long),
+            Origin::Environment(variable) => variable.clone(),
+            Origin::ConfigFile(path, line) => {
+                format!("--{} ({} line {})", self.flag.long, path, line)
+            }
+        }
+    }
+}
+
+impl Deref for Given {
+    type Target = Flag;
+
+    fn deref(&self) -> &Flag {
+        self.flag
+    }
+}
+
+// Splits arguments into options with their values and inputs. Everything
+// after `--` is an input.
+fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Vec<Arg>, String> {
+    let mut parsed = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            parsed.extend(args.map(Arg::Input));
+            break;
+        }
+
+        let (flag, attached) = if let Some(long) = arg.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            (find(name).ok_or_else(|| unknown(&arg, name))?, value)
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            // `-j4` and `-j 4` both work.
+            let mut chars = arg[1..].chars();
+            let short = chars.next();
+            let rest = chars.as_str();
+            let flag = FLAGS
+                .iter()
+                .find(|flag| flag.short == short)
+                .filter(|flag| flag.value.is_some() || rest.is_empty())
+                .ok_or_else(|| format!("Unknown option {}", arg))?;
+            let value = (!rest.is_empty()).then(|| rest.to_string());
+            (flag, value)
+        } else {
+            parsed.push(Arg::Input(arg));
+            continue;
+        };
+
+        // Only defaults may be overridden; twice on one command line is
+        // most likely a mistake.
+        let repeated = parsed
+            .iter()
+            .any(|arg| matches!(arg, Arg::Flag(given, _) if given.long == flag.long));
+        if repeated && !flag.repeatable {
+            return Err(format!("--{} cannot be given more than once", flag.long));
+        }
+
+        let value = match (flag.value, attached) {
+            (Some(_), Some(value)) => value,
+            (Some(_), None) => args
+                .next()
+                .ok_or_else(|| format!("Missing value for --{}", flag.long))?,
+            (None, Some(_)) => return Err(format!("--{} does not take a value", flag.long)),
+            (None, None) => String::new(),
+        };
+        parsed.push(Arg::Flag(Given::new(flag, Origin::CommandLine), value));
+    }
+
+    Ok(parsed)
+}
+
+fn unknown(arg: &str, name: &str) -> String {
+    let arg = arg.split('=').next().unwrap_or(arg);
+    let closest = FLAGS
+        .iter()
+        .map(|flag| (edit_distance(name, flag.long), flag.long))
+        .min()
+        .filter(|&(distance, _)| distance <= 2);
+    match closest {
+        Some((_, long)) => format!("Unknown option {} (did you mean --{}?)", arg, long),
+        None => format!("Unknown option {}", arg),
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// The options and inputs of this run: defaults from the config file,
+/// then from the environment, then the command line. Later options
+/// override earlier defaults, and repeatable options add to them.
+pub fn args() -> Result<Vec<Arg>, String> {
+    with_defaults(parse(env::args().skip(1))?, config_file, || {
+        environment(|name| env::var_os(name))
+    })
+}
+
+// Puts the defaults before `command_line`, unless it asks for an action.
+fn with_defaults<C, E>(
+    command_line: Vec<Arg>,
+    config_file: C,
+    environment: E,
+) -> Result<Vec<Arg>, String>
+where
+    C: FnOnce() -> Result<Vec<Arg>, String>,
+    E: FnOnce() -> Result<Vec<Arg>, String>,
+{
+    let action = command_line
+        .iter()
+        .any(|arg| matches!(arg, Arg::Flag(flag, _) if ACTIONS.contains(&flag.long)));
+    if action {
+        return Ok(command_line);
+    }
+
+    let mut args = config_file()?;
+    args.extend(environment()?);
+    args.extend(command_line);
+    Ok(args)
+}
+
+// $WORDFREQ_CONFIG, which must exist, or else wordfreq/config in the XDG
+// config directory, which may not.
+fn config_path() -> Option<(PathBuf, bool)> {
+    if let Some(path) = env::var_os("WORDFREQ_CONFIG").filter(|path| !path.is_empty()) {
+        return Some((PathBuf::from(path), true));
+    }
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some((config_home.join("wordfreq").join("config"), false))
+}
+
+fn config_file() -> Result<Vec<Arg>, String> {
+    let Some((path, required)) = config_path() else {
+        return Ok(Vec::new());
+    };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound && !required => return Ok(Vec::new()),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    config_defaults(&text, &path.display().to_string())
+}
+
+// One option per line, `name = value` or a bare `name` for a switch,
+// with `#` starting a comment line.
+fn config_defaults(text: &str, path: &str) -> Result<Vec<Arg>, String> {
+    let mut args: Vec<Arg> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, value) = match line.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (line, None),
+        };
+        let flag = find(name)
+            .filter(|flag| !ACTIONS.contains(&flag.long))
+            .ok_or_else(|| format!("{} line {}: Unknown option {}", path, index + 1, name))?;
+        let given = Given::new(flag, Origin::ConfigFile(path.to_string(), index + 1));
+
+        let repeated = args
+            .iter()
+            .any(|arg| matches!(arg, Arg::Flag(other, _) if other.long == flag.long));
+        if repeated && !flag.repeatable {
+            return Err(format!("{} cannot be given more than once", given.name()));
+        }
+        args.extend(default(given, value)?);
+    }
+    Ok(args)
+}
+
+// WORDFREQ_TOP=20, WORDFREQ_IGNORE_CASE=1 and so on, looked up by `var`.
+// Empty variables are ignored.
+fn environment<F: Fn(&str) -> Option<OsString>>(var: F) -> Result<Vec<Arg>, String> {
+    let mut args = Vec::new();
+    for flag in FLAGS.iter().filter(|flag| !ACTIONS.contains(&flag.long)) {
+        let name = format!("WORDFREQ_{}", flag.long.to_uppercase().replace('-', "_"));
+        let Some(value) = var(&name).filter(|value| !value.is_empty()) else {
+            continue;
+        };
+        let value = value
+            .into_string()
+            .map_err(|_| format!("{} is not valid UTF-8", name))?;
+        args.extend(default(
+            Given::new(flag, Origin::Environment(name)),
+            Some(&value),
+        )?);
+    }
+    Ok(args)
+}
+
+// A default for an option. Switches are turned on bare or by a true value.
+fn default(given: Given, value: Option<&str>) -> Result<Option<Arg>, String> {
+    match (given.value, value) {
+        (Some(_), Some(value)) => Ok(Some(Arg::Flag(given, value.to_string()))),
+        (Some(_), None) => Err(format!("Missing value for {}", given.name())),
+        (None, None) => Ok(Some(Arg::Flag(given, String::new()))),
+        (None, Some(value)) => match value {
+            "true" | "yes" | "1" => Ok(Some(Arg::Flag(given, String::new()))),
+            "false" | "no" | "0" | "" => Ok(None),
+            _ => Err(format!(
+                "Invalid value for {}: {} (true or false)",
+                given.name(),
+                value
+            )),
+        },
+    }
+}
+
+/// Reports invalid usage and exits with status 2.
+pub fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(2);
+}
+
+/// Reports a value `flag` does not accept, with the accepted ones.
+pub fn invalid(flag: &Given, value: &str) -> ! {
+    if flag.choices.is_empty() {
+        fail(&format!("Invalid value for {}: {}", flag.name(), value))
+    }
+    fail(&format!(
+        "Invalid value for {}: {} (one of: {})",
+        flag.name(),
+        value,
+        flag.choices.join(", ")
+    ))
+}
+
+/// Parses the value of `flag` as a number of at least `min`.
+pub fn number<T: FromStr + PartialOrd + Display>(flag: &Given, value: &str, min: T) -> T {
+    match value.parse() {
+        Ok(n) if n >= min => n,
+        Ok(_) => fail(&format!("{} must be at least {}", flag.name(), min)),
+        Err(_) => invalid(flag, value),
+    }
+}
+
+pub fn print_help() {
+    println!("Usage: wordfreq [OPTIONS] [PATH]...");
+    println!();
+    println!("Count word frequency in text");
+    println!();
+    println!("Arguments:");
+    println!("Files or directories to analyze (or use stdin, or - for stdin)");
+    println!();
+    println!("Options:");
+    for flag in FLAGS {
+        println!("{} {}", flag.names(), flag.help);
+        for line in flag.details {
+            println!("  {}", line);
+        }
+    }
+    println!();
+    println!("Options also take --name=VALUE. Defaults for them are read from $WORDFREQ_CONFIG");
+    println!("or ~/.config/wordfreq/config (one `name = value` per line) and from WORDFREQ_NAME");
+    println!("environment variables; see `wordfreq --man`.");
+    println!();
+    println!("Exit status: 0 on success, 1 if reading or writing failed, 2 for invalid usage.");
+}
+
+/// A completion script for `shell`: bash, zsh or fish.
+pub fn completions(shell: &str) -> Option<String> {
+    match shell {
+        "bash" => Some(bash_completions()),
+        "zsh" => Some(zsh_completions()),
+        "fish" => Some(fish_completions()),
+        _ => None,
+    }
+}
+
+fn bash_completions() -> String {
+    let mut out = String::from(
+        "_wordfreq() {\n    local cur=\"${COMP_WORDS[COMP_CWORD]}\"\n    local prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n    case \"$prev\" in\n",
+    );
+    for flag in FLAGS.iter().filter(|flag| flag.value.is_some()) {
+        let mut names = format!("--{}", flag.long);
+        if let Some(short) = flag.short {
+            names += &format!("|-{}", short);
+        }
+        let reply = match (flag.takes_file(), flag.choices.is_empty()) {
+            (true, true) => "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string(),
+            (true, false) => format!(
+                "COMPREPLY=($(compgen -f -W \"{}\" -- \"$cur\"))",
+                flag.choices.join(" ")
+            ),
+            (false, false) => format!(
+                "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+                flag.choices.join(" ")
+            ),
+            (false, true) => "COMPREPLY=()".to_string(),
+        };
+        out += &format!(
+            "        {})\n            {}\n            return ;;\n",
+            names, reply
+        );
+    }
+    let options: Vec<String> = FLAGS
+        .iter()
+        .flat_map(|flag| {
+            let short = flag.short.map(|short| format!("-{}", short));
+            short.into_iter().chain([format!("--{}", flag.long)])
+        })
+        .collect();
+    out += &format!(
+        "    esac\n    if [[ \"$cur\" == -* ]]; then\n        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n    else\n        COMPREPLY=($(compgen -f -- \"$cur\"))\n    fi\n}}\ncomplete -o filenames -F _wordfreq wordfreq\n",
+        options.join(" ")
+    );
+    out
+}
+
+fn zsh_completions() -> String {
+    // Brackets delimit the description in an _arguments spec.
+    let escape = |s: &str| {
+        s.replace('\\', "\\\\")
+            .replace('\'', "'\\''")
+            .replace('[', "\\[")
+            .replace(']', "\\]")
+    };
+
+    let mut out = String::from("#compdef wordfreq\n\n_arguments -s \\\n");
+    for flag in FLAGS {
+        let repeat = if flag.repeatable { "*" } else { "" };
+        let help = escape(flag.help);
+        let action = match flag.value {
+            None => String::new(),
+            Some(value) if flag.takes_file() && !flag.choices.is_empty() => format!(
+                ":{}:_alternative 'lists:list:({})' 'files:file:_files'",
+                value,
+                flag.choices.join(" ")
+            ),
+            Some(value) if flag.takes_file() => format!(":{}:_files", value),
+            Some(value) if !flag.choices.is_empty() => {
+                format!(":{}:({})", value, flag.choices.join(" "))
+            }
+            Some(value) => format!(":{}: ", value),
+        };
+        let action = action.replace('\'', "'\\''");
+        let equals = if flag.value.is_some() { "=" } else { "" };
+        match flag.short {
+            Some(short) => {
+                let plus = if flag.value.is_some() { "+" } else { "" };
+                out += &format!(
+                    "  '(-{s} --{l})'{{-{s}{plus},--{l}{equals}}}'[{help}]{action}' \\\n",
+                    s = short,
+                    l = flag.long,
+                );
+            }
+            None => {
+                out += &format!(
+                    "  '{repeat}--{l}{equals}[{help}]{action}' \\\n",
+                    l = flag.long
+                );
+            }
+        }
+    }
+    out + "  '*:PATH:_files'\n"
+}
+
+fn fish_completions() -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"));
+
+    let mut out = String::new();
+    for flag in FLAGS {
+        out += &format!("complete -c wordfreq -l {}", flag.long);
+        if let Some(short) = flag.short {
+            out += &format!(" -s {}", short);
+        }
+        if flag.value.is_some() {
+            out += if flag.takes_file() { " -r -F" } else { " -x" };
+        }
+        if !flag.choices.is_empty() {
+            out += &format!(" -a {}", quote(&flag.choices.join(" ")));
+        }
+        out += &format!(" -d {}\n", quote(flag.help));
+    }
+    out
+}
+
+/// The manual page, in roff.
+pub fn man_page() -> String {
+    // Backslashes and dashes are special, and so are a dot or quote
+    // starting a line.
+    let escape = |s: &str| {
+        let s = s.replace('\\', "\\e").replace('-', "\\-");
+        if s.starts_with(['.', '\'']) {
+            format!("\\&{}", s)
+        } else {
+            s
+        }
+    };
+
+    let mut out = format!(
+        ".TH WORDFREQ 1 \"\" \"wordfreq {}\" \"User Commands\"\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    out += ".SH NAME\nwordfreq \\- count word frequency in text\n";
+    out += ".SH SYNOPSIS\n.B wordfreq\n[\\fIOPTIONS\\fR] [\\fIPATH\\fR]...\n";
+    out += ".SH DESCRIPTION\n";
+    out += "Counts the words of the files and directories given, of standard input \
+            without any or for \\fB\\-\\fR, and prints the most frequent.\n";
+    out += ".SH OPTIONS\n";
+    for flag in FLAGS {
+        out += ".TP\n";
+        if let Some(short) = flag.short {
+            out += &format!("\\fB\\-{}\\fR, ", short);
+        }
+        out += &format!("\\fB\\-\\-{}\\fR", escape(flag.long));
+        if let Some(value) = flag.value {
+            out += &format!(" \\fI{}\\fR", value);
+        }
+        out += &format!("\n{}\n", escape(flag.help));
+        for line in flag.details {
+            out += &format!(".br\n{}\n", escape(line));
+        }
+    }
+    out += ".SH CONFIGURATION\n";
+    out += "Defaults for options are read from the file named by \\fBWORDFREQ_CONFIG\\fR, \
+            or else from \\fI$XDG_CONFIG_HOME/wordfreq/config\\fR \
+            (\\fI~/.config/wordfreq/config\\fR). \
+            Each line is \\fIname\\fR \\fB=\\fR \\fIvalue\\fR, or a bare \\fIname\\fR \
+            to turn a switch on; lines starting with \\fB#\\fR are comments.\n";
+    out += ".PP\nEach option can also be given a default by a \\fBWORDFREQ_\\fR\\fINAME\\fR \
+            environment variable, the option's name in capitals with dashes as underscores, \
+            e.g. \\fBWORDFREQ_TOP=20\\fR or \\fBWORDFREQ_IGNORE_CASE=1\\fR.\n";
+    out += ".PP\nThe environment overrides the config file and the command line overrides both; \
+            repeatable options add to their defaults. Other options can be given only once \
+            on the command line and once in the config file. \\fB\\-\\-no\\-config\\fR \
+            ignores all defaults.\n";
+    out += ".SH EXIT STATUS\n.TP\n0\nSuccess.\n.TP\n1\nAn input could not be read or \
+            an output could not be written.\n.TP\n2\nInvalid usage: an unknown option, \
+            a missing or invalid value, options that cannot be combined, or an invalid \
+            default.\n";
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Each argument as `name=value`, with the name saying where it came
+    // from, or as the input itself.
+    fn shown(args: Result<Vec<Arg>, String>) -> Result<Vec<String>, String> {
+        Ok(args?
+            .iter()
+            .map(|arg| match arg {
+                Arg::Flag(given, value) => format!("{}={}", given.name(), value),
+                Arg::Input(input) => input.clone(),
+            })
+            .collect())
+    }
+
+    fn parsed(args: &[&str]) -> Result<Vec<String>, String> {
+        shown(parse(args.iter().map(|arg| arg.to_string())))
+    }
+
+    #[test]
+    fn parses_attached_values_switches_and_inputs() {
+        assert_eq!(
+            parsed(&[
+                "--top=3",
+                "-j4",
+                "--format",
+                "csv",
+                "--ignore-case",
+                "a.txt"
+            ])
+            .unwrap(),
+            [
+                "--top=3",
+                "--jobs=4",
+                "--format=csv",
+                "--ignore-case=",
+                "a.txt"
+            ]
+        );
+        assert_eq!(
+            parsed(&["--kwic", "a", "--kwic=b", "--", "--top", "-"]).unwrap(),
+            ["--kwic=a", "--kwic=b", "--top", "-"]
+        );
+        assert_eq!(parsed(&["-"]).unwrap(), ["-"]);
+    }
+
+    #[test]
+    fn rejects_malformed_options() {
+        let error = |args: &[&str]| parsed(args).unwrap_err();
+        assert_eq!(error(&["--top"]), "Missing value for --top");
+        assert_eq!(error(&["-j"]), "Missing value for --jobs");
+        assert_eq!(
+            error(&["--ignore-case=yes"]),
+            "--ignore-case does not take a value"
+        );
+        assert_eq!(error(&["-hx"]), "Unknown option -hx");
+        assert_eq!(
+            error(&["--tpo=3"]),
+            "Unknown option --tpo (did you mean --top?)"
+        );
+        assert_eq!(
+            error(&["--top", "3", "--top=5"]),
+            "--top cannot be given more than once"
+        );
+        assert_eq!(
+            error(&["-j4", "-j", "2", "a.txt"]),
+            "--jobs cannot be given more than once"
+        );
+    }
+
+    #[test]
+    fn defaults_come_before_the_command_line() {
+        let config = "# defaults\ntop = 5\nignore-case\nlossy = false\nkwic = a\n";
+        let environment = |name: &str| match name {
+            "WORDFREQ_TOP" => Some(OsString::from("7")),
+            "WORDFREQ_KWIC" => Some(OsString::from("b")),
+            "WORDFREQ_LOSSY" => Some(OsString::from("")),
+            _ => None,
+        };
+        let command_line = || parse(["--top".to_string(), "9".to_string()]).unwrap();
+
+        let args = with_defaults(
+            command_line(),
+            || config_defaults(config, "config"),
+            || super::environment(environment),
+        );
+        assert_eq!(
+            shown(args).unwrap(),
+            [
+                "--top (config line 2)=5",
+                "--ignore-case (config line 3)=",
+                "--kwic (config line 5)=a",
+                "WORDFREQ_TOP=7",
+                "WORDFREQ_KWIC=b",
+                "--top=9",
+            ]
+        );
+
+        let help = parse(["--help".to_string()]).unwrap();
+        let args = with_defaults(help, || unreachable!(), || unreachable!());
+        assert_eq!(shown(args).unwrap(), ["--help="]);
+    }
+
+    #[test]
+    fn invalid_defaults_name_their_origin() {
+        let top = find("top").unwrap();
+        let switch = find("ignore-case").unwrap();
+        let env = |flag| Given::new(flag, Origin::Environment("WORDFREQ_X".to_string()));
+
+        assert!(default(env(switch), Some("no")).unwrap().is_none());
+        assert_eq!(
+            default(env(switch), Some("maybe")).err().unwrap(),
+            "Invalid value for WORDFREQ_X: maybe (true or false)"
+        );
+        assert_eq!(
+            default(env(top), None).err().unwrap(),
+            "Missing value for WORDFREQ_X"
+        );
+        assert_eq!(
+            config_defaults("\ntop = 1\ntop = 2\n", "wf.conf")
+                .err()
+                .unwrap(),
+            "--top (wf.conf line 3) cannot be given more than once"
+        );
+        assert_eq!(
+            config_defaults("help\n", "wf.conf").err().unwrap(),
+            "wf.conf line 1: Unknown option help"
+        );
+    }
+}
@@ -0,0 +1,777 @@
+use std::path::Path;
+
+use crate::{Options, Unit, WordCounter};
+
+/// Programming languages the code lexer knows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    C,
+    Java,
+    Go,
+}
+
+impl Language {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "rust" => Some(Language::Rust),
+            "python" => Some(Language::Python),
+            "javascript" | "typescript" => Some(Language::JavaScript),
+            "c" | "cpp" => Some(Language::C),
+            "java" => Some(Language::Java),
+            "go" => Some(Language::Go),
+            _ => None,
+        }
+    }
+
+    /// Guesses the language from a file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        match extension {
+            "rs" => Some(Language::Rust),
+            "py" | "pyi" => Some(Language::Python),
+            "js" | "mjs" | "cjs" | "jsx" | "ts" | "mts" | "cts" | "tsx" => {
+                Some(Language::JavaScript)
+            }
+            "c" | "h" | "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => Some(Language::C),
+            "java" => Some(Language::Java),
+            "go" => Some(Language::Go),
+            _ => None,
+        }
+    }
+
+    fn syntax(self) -> &'static Syntax {
+        match self {
+            Language::Rust => &RUST,
+            Language::Python => &PYTHON,
+            Language::JavaScript => &JAVASCRIPT,
+            Language::C => &C,
+            Language::Java => &JAVA,
+            Language::Go => &GO,
+        }
+    }
+}
+
+/// What part of the source a token came from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Category {
+    Identifier,
+    Keyword,
+    Comment,
+    String,
+}
+
+impl Category {
+    pub const ALL: [Category; 4] = [
+        Category::Identifier,
+        Category::Keyword,
+        Category::Comment,
+        Category::String,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Identifier => "identifiers",
+            Category::Keyword => "keywords",
+            Category::Comment => "comments",
+            Category::String => "strings",
+        }
+    }
+}
+
+struct Syntax {
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    nested_comments: bool,
+    /// Quotes that delimit strings (or character literals, counted as
+    /// strings). Strings end at a newline unless `multiline` lists them.
+    quotes: &'static [char],
+    multiline: &'static [char],
+    /// Identifiers that turn a following quote into a string prefix, and
+    /// whether the prefix makes the string raw.
+    string_prefixes: &'static [(&'static str, bool)],
+    triple_quotes: bool,
+    /// Rust's `'a` lifetimes share the quote with character literals.
+    lifetimes: bool,
+    /// Characters besides letters, digits and `_` allowed in identifiers.
+    identifier_extra: &'static [char],
+    keywords: &'static [&'static str],
+}
+
+const RUST: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested_comments: true,
+    quotes: &['"', '\''],
+    multiline: &['"'],
+    string_prefixes: &[
+        ("b", false),
+        ("c", false),
+        ("r", true),
+        ("br", true),
+        ("cr", true),
+    ],
+    triple_quotes: false,
+    lifetimes: true,
+    identifier_extra: &[],
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+        "true", "type", "unsafe", "use", "where", "while", "yield",
+    ],
+};
+
+const PYTHON: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comment: None,
+    nested_comments: false,
+    quotes: &['"', '\''],
+    multiline: &[],
+    string_prefixes: &[
+        ("b", false),
+        ("f", false),
+        ("u", false),
+        ("r", true),
+        ("br", true),
+        ("rb", true),
+        ("fr", true),
+        ("rf", true),
+    ],
+    triple_quotes: true,
+    lifetimes: false,
+    identifier_extra: &[],
+    keywords: &[
+        "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+        "continue", "def", "del", "elif", "else", "except", "finally", "for", "from", "global",
+        "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return",
+        "try", "while", "with", "yield", "self",
+    ],
+};
+
+const JAVASCRIPT: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested_comments: false,
+    quotes: &['"', '\'', '`'],
+    multiline: &['`'],
+    string_prefixes: &[],
+    triple_quotes: false,
+    lifetimes: false,
+    identifier_extra: &['$'],
+    keywords: &[
+        "abstract",
+        "as",
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "debugger",
+        "declare",
+        "default",
+        "delete",
+        "do",
+        "else",
+        "enum",
+        "export",
+        "extends",
+        "false",
+        "finally",
+        "for",
+        "from",
+        "function",
+        "get",
+        "if",
+        "implements",
+        "import",
+        "in",
+        "instanceof",
+        "interface",
+        "let",
+        "new",
+        "null",
+        "of",
+        "private",
+        "protected",
+        "public",
+        "readonly",
+        "return",
+        "set",
+        "static",
+        "super",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "type",
+        "typeof",
+        "undefined",
+        "var",
+        "void",
+        "while",
+        "with",
+        "yield",
+    ],
+};
+
+const C: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested_comments: false,
+    quotes: &['"', '\''],
+    multiline: &[],
+    string_prefixes: &[("L", false), ("u", false), ("U", false), ("u8", false)],
+    triple_quotes: false,
+    lifetimes: false,
+    identifier_extra: &[],
+    keywords: &[
+        "auto",
+        "bool",
+        "break",
+        "case",
+        "catch",
+        "char",
+        "class",
+        "const",
+        "constexpr",
+        "continue",
+        "default",
+        "define",
+        "delete",
+        "do",
+        "double",
+        "else",
+        "endif",
+        "enum",
+        "explicit",
+        "extern",
+        "false",
+        "float",
+        "for",
+        "friend",
+        "goto",
+        "if",
+        "ifdef",
+        "ifndef",
+        "include",
+        "inline",
+        "int",
+        "long",
+        "namespace",
+        "new",
+        "noexcept",
+        "nullptr",
+        "operator",
+        "override",
+        "pragma",
+        "private",
+        "protected",
+        "public",
+        "register",
+        "return",
+        "short",
+        "signed",
+        "sizeof",
+        "static",
+        "struct",
+        "switch",
+        "template",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "typedef",
+        "typename",
+        "union",
+        "unsigned",
+        "using",
+        "virtual",
+        "void",
+        "volatile",
+        "while",
+    ],
+};
+
+const JAVA: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested_comments: false,
+    quotes: &['"', '\''],
+    multiline: &[],
+    string_prefixes: &[],
+    triple_quotes: true,
+    lifetimes: false,
+    identifier_extra: &['$'],
+    keywords: &[
+        "abstract",
+        "assert",
+        "boolean",
+        "break",
+        "byte",
+        "case",
+        "catch",
+        "char",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "do",
+        "double",
+        "else",
+        "enum",
+        "extends",
+        "false",
+        "final",
+        "finally",
+        "float",
+        "for",
+        "if",
+        "implements",
+        "import",
+        "instanceof",
+        "int",
+        "interface",
+        "long",
+        "native",
+        "new",
+        "null",
+        "package",
+        "private",
+        "protected",
+        "public",
+        "record",
+        "return",
+        "short",
+        "static",
+        "super",
+        "switch",
+        "synchronized",
+        "this",
+        "throw",
+        "throws",
+        "true",
+        "try",
+        "var",
+        "void",
+        "volatile",
+        "while",
+    ],
+};
+
+const GO: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested_comments: false,
+    quotes: &['"', '\'', '`'],
+    multiline: &['`'],
+    string_prefixes: &[],
+    triple_quotes: false,
+    lifetimes: false,
+    identifier_extra: &[],
+    keywords: &[
+        "break",
+        "case",
+        "chan",
+        "const",
+        "continue",
+        "default",
+        "defer",
+        "else",
+        "fallthrough",
+        "false",
+        "for",
+        "func",
+        "go",
+        "goto",
+        "if",
+        "import",
+        "interface",
+        "map",
+        "nil",
+        "package",
+        "range",
+        "return",
+        "select",
+        "struct",
+        "switch",
+        "true",
+        "type",
+        "var",
+    ],
+};
+
+/// Splits `source` into identifiers, keywords, and the text of comments and
+/// string literals, calling `f` with each in order. Numbers and punctuation
+/// are skipped. The lexer is forgiving: unterminated comments and strings
+/// run to the end of the input (or line).
+pub fn lex<F: FnMut(Category, &str)>(source: &str, language: Language, mut f: F) {
+    let syntax = language.syntax();
+    let mut i = 0;
+
+    while i < source.len() {
+        let rest = &source[i..];
+        let c = rest.chars().next().unwrap();
+
+        if let Some(prefix) = syntax.line_comments.iter().find(|p| rest.starts_with(**p)) {
+            let end = rest.find('\n').unwrap_or(rest.len());
+            f(Category::Comment, &rest[prefix.len()..end]);
+            i += end;
+            continue;
+        }
+
+        if let Some((open, close)) = syntax.block_comment {
+            if rest.starts_with(open) {
+                let end = block_comment_end(rest, open, close, syntax.nested_comments);
+                let body_end = if rest[..end].ends_with(close) && end >= open.len() + close.len() {
+                    end - close.len()
+                } else {
+                    end
+                };
+                f(Category::Comment, &rest[open.len()..body_end]);
+                i += end;
+                continue;
+            }
+        }
+
+        if syntax.quotes.contains(&c) {
+            if syntax.lifetimes && c == '\'' && !is_char_literal(rest) {
+                // A lifetime or loop label: skip the quote and the name.
+                i += 1 + identifier_len(&rest[1..], syntax);
+                continue;
+            }
+            let (body, len) = string_literal(rest, syntax, false);
+            f(Category::String, body);
+            i += len;
+            continue;
+        }
+
+        if is_identifier_start(c, syntax) {
+            let len = identifier_len(rest, syntax);
+            let word = &rest[..len];
+            let after = &rest[len..];
+
+            let prefix = syntax.string_prefixes.iter().find(|(p, _)| *p == word);
+            let quote = after.chars().next().filter(|&q| {
+                syntax.quotes.contains(&q)
+                    && !(syntax.lifetimes && q == '\'' && !is_char_literal(after))
+            });
+            if let (Some(&(_, raw)), Some(_)) = (prefix, quote) {
+                let (body, string_len) = string_literal(after, syntax, raw);
+                f(Category::String, body);
+                i += len + string_len;
+                continue;
+            }
+            if syntax.lifetimes && word.starts_with('r') && raw_hashes(word, after) {
+                let (body, string_len) = rust_raw_string(after);
+                f(Category::String, body);
+                i += len + string_len;
+                continue;
+            }
+
+            if syntax.keywords.contains(&word) {
+                f(Category::Keyword, word);
+            } else {
+                f(Category::Identifier, word);
+            }
+            i += len;
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            // Numbers, including suffixes like `10u8` or `0x1F`.
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            i += len;
+            continue;
+        }
+
+        i += c.len_utf8();
+    }
+}
+
+fn is_identifier_start(c: char, syntax: &Syntax) -> bool {
+    c.is_alphabetic() || c == '_' || syntax.identifier_extra.contains(&c)
+}
+
+fn identifier_len(text: &str, syntax: &Syntax) -> usize {
+    text.find(|c: char| !(c.is_alphanumeric() || c == '_' || syntax.identifier_extra.contains(&c)))
+        .unwrap_or(text.len())
+}
+
+fn block_comment_end(text: &str, open: &str, close: &str, nested: bool) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        if rest.starts_with(open) && (nested || depth == 0) {
+            depth += 1;
+            i += open.len();
+        } else if rest.starts_with(close) {
+            depth -= 1;
+            i += close.len();
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += rest.chars().next().unwrap().len_utf8();
+        }
+    }
+    text.len()
+}
+
+// `'x'`, `'\n'` or `'\u{1F600}'`, as opposed to the lifetime in `&'a str`.
+fn is_char_literal(text: &str) -> bool {
+    let mut chars = text[1..].chars();
+    match chars.next() {
+        Some('\\') => true,
+        Some(_) => chars.next() == Some('\''),
+        None => false,
+    }
+}
+
+// Returns the body of the string starting at `text`, without quotes, and
+// the length of the whole literal.
+fn string_literal<'t>(text: &'t str, syntax: &Syntax, raw: bool) -> (&'t str, usize) {
+    let quote = text.chars().next().unwrap();
+
+    if syntax.triple_quotes {
+        let triple: String = [quote; 3].iter().collect();
+        if text.starts_with(&triple) {
+            let body = &text[3..];
+            return match find_closing(body, &triple, raw, true) {
+                Some(end) => (&body[..end], 3 + end + 3),
+                None => (body, text.len()),
+            };
+        }
+    }
+
+    let body = &text[quote.len_utf8()..];
+    let multiline = syntax.multiline.contains(&quote);
+    let mut closing = [0; 4];
+    let closing = quote.encode_utf8(&mut closing);
+    match find_closing(body, closing, raw, multiline) {
+        Some(end) => (&body[..end], quote.len_utf8() + end + closing.len()),
+        None => {
+            let end = if multiline {
+                body.len()
+            } else {
+                body.find('\n').unwrap_or(body.len())
+            };
+            (&body[..end], quote.len_utf8() + end)
+        }
+    }
+}
+
+fn find_closing(body: &str, closing: &str, raw: bool, multiline: bool) -> Option<usize> {
+    let mut chars = body.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if body[i..].starts_with(closing) {
+            return Some(i);
+        }
+        if c == '\n' && !multiline {
+            return None;
+        }
+        if c == '\\' && !raw {
+            chars.next();
+        }
+    }
+    None
+}
+
+// `r#"..."#` with any number of hashes; `r"..."` is a plain prefix.
+fn raw_hashes(word: &str, after: &str) -> bool {
+    matches!(word, "r" | "br" | "cr") && {
+        let hashes = after.len() - after.trim_start_matches('#').len();
+        hashes > 0 && after[hashes..].starts_with('"')
+    }
+}
+
+fn rust_raw_string(text: &str) -> (&str, usize) {
+    let hashes = text.len() - text.trim_start_matches('#').len();
+    let body = &text[hashes + 1..];
+    let closing = format!("\"{}", "#".repeat(hashes));
+    match body.find(&closing) {
+        Some(end) => (&body[..end], hashes + 1 + end + closing.len()),
+        None => (body, text.len()),
+    }
+}
+
+fn without_escapes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            chars.next();
+            out.push(' ');
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Splits an identifier into the words of its snake_case or camelCase
+/// parts: `parseHTTPResponse_v2` gives `parse`, `HTTP`, `Response`, `v2`.
+pub fn split_identifier(identifier: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+
+    for piece in identifier.split(['_', '$']).filter(|p| !p.is_empty()) {
+        let chars: Vec<(usize, char)> = piece.char_indices().collect();
+        let mut start = 0;
+        for k in 1..chars.len() {
+            let (index, c) = chars[k];
+            let prev = chars[k - 1].1;
+            let next = chars.get(k + 1).map(|&(_, c)| c);
+            let boundary = c.is_uppercase()
+                && (prev.is_lowercase()
+                    || prev.is_ascii_digit()
+                    || prev.is_uppercase() && next.is_some_and(|n| n.is_lowercase()));
+            if boundary {
+                parts.push(&piece[start..index]);
+                start = index;
+            }
+        }
+        parts.push(&piece[start..]);
+    }
+
+    parts
+}
+
+/// Counts the identifiers, keywords, comments and strings of source files
+/// separately. Identifiers and keywords are counted whole (or split into
+/// their parts); comments and strings are tokenized like plain text.
+pub struct CodeCounter {
+    counters: [WordCounter; 4],
+    split_identifiers: bool,
+}
+
+impl CodeCounter {
+    pub fn new(options: &Options, split_identifiers: bool) -> Self {
+        Self {
+            counters: Category::ALL.map(|_| WordCounter::new(options)),
+            split_identifiers,
+        }
+    }
+
+    /// Checks that `options` count single words, the only unit tokens of
+    /// code are counted in.
+    pub fn validate(options: &Options) -> Result<(), String> {
+        if options.ngram > 1 || options.unit != Unit::Word {
+            return Err("code can only be counted as single words".to_string());
+        }
+        Ok(())
+    }
+
+    /// Counts one whole source file.
+    pub fn feed_source(&mut self, source: &str, language: Language) {
+        lex(source, language, |category, text| {
+            let counter = &mut self.counters[category as usize];
+            match category {
+                Category::Identifier if self.split_identifiers => {
+                    for part in split_identifier(text) {
+                        counter.count_word(part);
+                    }
+                }
+                Category::Identifier | Category::Keyword => counter.count_word(text),
+                Category::Comment => {
+                    counter.feed(text);
+                    // Keeps the words of neighbouring literals apart.
+                    counter.feed("\n");
+                }
+                Category::String => {
+                    // Escapes like `\n` are not words.
+                    counter.feed(&without_escapes(text));
+                    counter.feed("\n");
+                }
+            }
+        });
+    }
+
+    pub fn finish(&mut self) {
+        for counter in &mut self.counters {
+            counter.finish();
+        }
+    }
+
+    pub fn merge(&mut self, other: CodeCounter) {
+        for (counter, other) in self.counters.iter_mut().zip(other.counters) {
+            counter.merge(other);
+        }
+    }
+
+    pub fn counter(&self, category: Category) -> &WordCounter {
+        &self.counters[category as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str, language: Language) -> Vec<(Category, String)> {
+        let mut tokens = Vec::new();
+        lex(source, language, |category, text| {
+            tokens.push((category, text.trim().to_string()))
+        });
+        tokens
+    }
+
+    #[test]
+    fn rust_separates_comments_strings_and_lifetimes() {
+        let source = "fn get<'a>(s: &'a str) -> char { /* outer /* inner */ */ 'x' } // done\nr#\"raw \"q\"\"#";
+        let tokens = tokens(source, Language::Rust);
+        let expected = [
+            (Category::Keyword, "fn"),
+            (Category::Identifier, "get"),
+            (Category::Identifier, "s"),
+            (Category::Identifier, "str"),
+            (Category::Identifier, "char"),
+            (Category::Comment, "outer /* inner */"),
+            (Category::String, "x"),
+            (Category::Comment, "done"),
+            (Category::String, "raw \"q\""),
+        ];
+        let expected: Vec<(Category, String)> =
+            expected.iter().map(|(c, t)| (*c, t.to_string())).collect();
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn python_handles_prefixes_and_triple_quotes() {
+        let source = "def f(x):\n    \"\"\"Doc \"string\".\"\"\"\n    return rb'\\d' # note";
+        let tokens = tokens(source, Language::Python);
+        let categories: Vec<Category> = tokens.iter().map(|t| t.0).collect();
+        assert_eq!(
+            categories,
+            [
+                Category::Keyword,
+                Category::Identifier,
+                Category::Identifier,
+                Category::String,
+                Category::Keyword,
+                Category::String,
+                Category::Comment,
+            ]
+        );
+        assert_eq!(tokens[3].1, "Doc \"string\".");
+        assert_eq!(tokens[5].1, "\\d");
+    }
+
+    #[test]
+    fn identifiers_split_at_case_and_underscores() {
+        assert_eq!(
+            split_identifier("parseHTTPResponse_v2"),
+            ["parse", "HTTP", "Response", "v2"]
+        );
+        assert_eq!(split_identifier("__init__"), ["init"]);
+        assert_eq!(split_identifier("getX"), ["get", "X"]);
+    }
+}
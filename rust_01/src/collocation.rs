@@ -0,0 +1,338 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+
+use crate::counter::ends_sentence;
+use crate::{Options, Segmenter, Unit, WordCounter};
+
+/// Measure of association [`Collocations`] are ranked by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Association {
+    /// Pointwise mutual information, which favours rare, exclusive pairs.
+    Pmi,
+    /// t-score, which favours frequent pairs.
+    TScore,
+    /// Log-likelihood ratio (G²).
+    LogLikelihood,
+}
+
+impl Association {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pmi" => Some(Association::Pmi),
+            "t-score" => Some(Association::TScore),
+            "ll" => Some(Association::LogLikelihood),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Association::Pmi => "PMI",
+            Association::TScore => "t-score",
+            Association::LogLikelihood => "log-likelihood",
+        }
+    }
+}
+
+/// A pair of words found close together, in the order they occur.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collocation<'a> {
+    pub rank: usize,
+    pub first: &'a str,
+    pub second: &'a str,
+    /// Times `second` followed `first` within the span.
+    pub count: usize,
+    /// Base-2 log of the ratio of the observed to the expected count.
+    pub pmi: f64,
+    pub t_score: f64,
+    /// G², negative for pairs seen less often than expected.
+    pub log_likelihood: f64,
+}
+
+/// Counts pairs of words that occur within a span of each other, to find
+/// the pairs that co-occur more often than chance.
+///
+/// Words are normalized, filtered and stemmed like the words a
+/// [`WordCounter`] with the same options counts. The span is measured in
+/// counted words: a stop word between two words does not keep them apart.
+/// With `sentence_boundaries`, pairs do not cross a sentence end.
+///
+/// The expected count of a pair comes from how often each word starts and
+/// ends a pair, so all three measures compare the same 2×2 table.
+pub struct Collocations {
+    matcher: WordCounter,
+    segmenter: Segmenter,
+    span: usize,
+    sentence_boundaries: bool,
+    ids: HashMap<String, usize>,
+    words: Vec<String>,
+    word_counts: Vec<usize>,
+    pairs: HashMap<(usize, usize), usize>,
+    // The last `span` counted words, most recent last.
+    recent: VecDeque<usize>,
+}
+
+impl Collocations {
+    /// Pairs each word with the words up to `span` counted words after it;
+    /// a span of 1 only pairs neighbours.
+    pub fn new(options: &Options, span: usize) -> Self {
+        Self {
+            matcher: WordCounter::new(options),
+            segmenter: Segmenter::new(options),
+            span,
+            sentence_boundaries: options.sentence_boundaries,
+            ids: HashMap::new(),
+            words: Vec::new(),
+            word_counts: Vec::new(),
+            pairs: HashMap::new(),
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Checks that `options` count single words, the only thing pairs are
+    /// made of.
+    pub fn validate(options: &Options) -> Result<(), String> {
+        if options.ngram > 1 || options.unit != Unit::Word {
+            return Err("collocations can only be found between single words".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn feed(&mut self, chunk: &str) {
+        if let Some(ready) = self.segmenter.push(chunk) {
+            self.count_segment(&ready);
+        }
+    }
+
+    pub fn finish(&mut self) {
+        let rest = self.segmenter.finish();
+        self.count_segment(&rest);
+        self.recent.clear();
+    }
+
+    fn count_segment(&mut self, text: &str) {
+        let text = self.matcher.normalize(text);
+        let mut last_end = 0;
+
+        // The loop needs `self` mutably; a clone shares the compiled pattern.
+        let tokenizer = self.matcher.tokenizer().clone();
+        for (start, word) in tokenizer.word_indices(&text) {
+            if self.sentence_boundaries && ends_sentence(&text[last_end..start]) {
+                self.recent.clear();
+            }
+            last_end = start + word.len();
+
+            if let Some(key) = self.matcher.normalized_key(word) {
+                self.add(&key);
+            }
+        }
+    }
+
+    fn add(&mut self, key: &str) {
+        let id = self.id(key);
+        self.word_counts[id] += 1;
+        for &previous in &self.recent {
+            *self.pairs.entry((previous, id)).or_insert(0) += 1;
+        }
+
+        self.recent.push_back(id);
+        if self.recent.len() > self.span {
+            self.recent.pop_front();
+        }
+    }
+
+    fn id(&mut self, word: &str) -> usize {
+        if let Some(&id) = self.ids.get(word) {
+            return id;
+        }
+        let id = self.words.len();
+        self.ids.insert(word.to_string(), id);
+        self.words.push(word.to_string());
+        self.word_counts.push(0);
+        id
+    }
+
+    /// Adds the counts of `other`, built with the same options and span.
+    /// No pair is made across the two.
+    pub fn merge(&mut self, other: Collocations) {
+        let ids: Vec<usize> = other
+            .words
+            .iter()
+            .zip(&other.word_counts)
+            .map(|(word, &count)| {
+                let id = self.id(word);
+                self.word_counts[id] += count;
+                id
+            })
+            .collect();
+        for ((first, second), count) in other.pairs {
+            *self.pairs.entry((ids[first], ids[second])).or_insert(0) += count;
+        }
+    }
+
+    /// Number of words counted.
+    pub fn total(&self) -> usize {
+        self.word_counts.iter().sum()
+    }
+
+    /// Number of pairs counted, each occurrence of a pair once.
+    pub fn pair_total(&self) -> usize {
+        self.pairs.values().sum()
+    }
+
+    pub fn word_count(&self, word: &str) -> usize {
+        self.ids.get(word).map_or(0, |&id| self.word_counts[id])
+    }
+
+    /// The pairs seen at least `min_count` times, made of words seen at
+    /// least `min_word_count` times each, most strongly associated first
+    /// by `by`, then by count and the words.
+    pub fn ranked(
+        &self,
+        by: Association,
+        min_count: usize,
+        min_word_count: usize,
+    ) -> Vec<Collocation<'_>> {
+        let mut as_first = vec![0; self.words.len()];
+        let mut as_second = vec![0; self.words.len()];
+        for (&(first, second), &count) in &self.pairs {
+            as_first[first] += count;
+            as_second[second] += count;
+        }
+        let total = self.pair_total() as f64;
+
+        let mut collocations: Vec<Collocation> = self
+            .pairs
+            .iter()
+            .filter(|&(&(first, second), &count)| {
+                count >= min_count
+                    && self.word_counts[first] >= min_word_count
+                    && self.word_counts[second] >= min_word_count
+            })
+            .map(|(&(first, second), &count)| {
+                let observed = count as f64;
+                let (row, column) = (as_first[first] as f64, as_second[second] as f64);
+                let expected = row * column / total;
+                Collocation {
+                    rank: 0,
+                    first: &self.words[first],
+                    second: &self.words[second],
+                    count,
+                    pmi: (observed / expected).log2(),
+                    t_score: (observed - expected) / observed.sqrt(),
+                    log_likelihood: log_likelihood(observed, row, column, total),
+                }
+            })
+            .collect();
+
+        let score = |c: &Collocation| match by {
+            Association::Pmi => c.pmi,
+            Association::TScore => c.t_score,
+            Association::LogLikelihood => c.log_likelihood,
+        };
+        collocations.sort_by(|x, y| {
+            score(y)
+                .partial_cmp(&score(x))
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| y.count.cmp(&x.count))
+                .then_with(|| (x.first, x.second).cmp(&(y.first, y.second)))
+        });
+        for (index, collocation) in collocations.iter_mut().enumerate() {
+            collocation.rank = index + 1;
+        }
+        collocations
+    }
+}
+
+// G² over the 2×2 table of pairs with and without each word, with
+// 0 * ln(0) taken as 0.
+fn log_likelihood(observed: f64, row: f64, column: f64, total: f64) -> f64 {
+    let cells = [
+        (observed, row, column),
+        (row - observed, row, total - column),
+        (column - observed, total - row, column),
+        (total - row - column + observed, total - row, total - column),
+    ];
+    let g2: f64 = cells
+        .iter()
+        .map(|&(cell, row, column)| {
+            if cell > 0.0 {
+                cell * (cell * total / (row * column)).ln()
+            } else {
+                0.0
+            }
+        })
+        .sum::<f64>()
+        * 2.0;
+
+    if observed < row * column / total {
+        -g2
+    } else {
+        g2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collocations(options: &Options, span: usize, text: &str) -> Collocations {
+        let mut collocations = Collocations::new(options, span);
+        collocations.feed(text);
+        collocations.finish();
+        collocations
+    }
+
+    #[test]
+    fn measures_compare_pairs_to_chance() {
+        let found = collocations(&Options::new(), 1, "a b a b c d");
+        assert_eq!(found.total(), 6);
+        assert_eq!(found.pair_total(), 5);
+
+        let ranked = found.ranked(Association::LogLikelihood, 2, 1);
+        assert_eq!(ranked.len(), 1);
+        let ab = &ranked[0];
+        assert_eq!((ab.first, ab.second, ab.count), ("a", "b", 2));
+        // Two of five pairs start with "a" and two end with "b".
+        let expected: f64 = 2.0 * 2.0 / 5.0;
+        assert!((ab.pmi - (2.0 / expected).log2()).abs() < 1e-12);
+        assert!((ab.t_score - (2.0 - expected) / 2f64.sqrt()).abs() < 1e-12);
+        assert!(ab.log_likelihood > 0.0);
+    }
+
+    #[test]
+    fn span_skips_filtered_words_and_stops_at_sentences() {
+        let options = Options::new()
+            .ignore_case(true)
+            .stop_words(["the".to_string()])
+            .sentence_boundaries(true);
+        let found = collocations(&options, 2, "New York, the city. New the York.");
+
+        let ranked = found.ranked(Association::Pmi, 1, 2);
+        let pairs: Vec<(&str, &str, usize)> = ranked
+            .iter()
+            .map(|c| (c.first, c.second, c.count))
+            .collect();
+        assert_eq!(pairs, [("new", "york", 2)]);
+        assert_eq!(found.word_count("city"), 1);
+        assert_eq!(found.word_count("the"), 0);
+    }
+
+    #[test]
+    fn merging_adds_counts_without_joining_inputs() {
+        let options = Options::new();
+        let mut merged = collocations(&options, 1, "x y z");
+        merged.merge(collocations(&options, 1, "z y x y"));
+
+        let ranked = merged.ranked(Association::TScore, 1, 1);
+        let pairs: Vec<(&str, &str, usize)> = ranked
+            .iter()
+            .map(|c| (c.first, c.second, c.count))
+            .collect();
+        assert_eq!(merged.pair_total(), 5);
+        assert!(pairs.contains(&("x", "y", 2)));
+        assert!(!pairs
+            .iter()
+            .any(|&(first, second, _)| (first, second) == ("z", "z")));
+    }
+}
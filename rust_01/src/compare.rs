@@ -0,0 +1,179 @@
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+
+use crate::WordCounter;
+
+/// How the words of a comparison are ranked.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CompareBy {
+    /// Difference of the raw counts.
+    Change,
+    /// Log ratio of the relative frequencies.
+    Relative,
+    /// Log-likelihood (G²) keyness.
+    LogLikelihood,
+    /// Pearson's chi-squared keyness.
+    ChiSquared,
+}
+
+impl CompareBy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "change" => Some(CompareBy::Change),
+            "relative" => Some(CompareBy::Relative),
+            "ll" => Some(CompareBy::LogLikelihood),
+            "chi2" => Some(CompareBy::ChiSquared),
+            _ => None,
+        }
+    }
+}
+
+/// One word of a comparison between a "before" and an "after" corpus.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WordChange<'a> {
+    pub word: &'a str,
+    pub before: usize,
+    pub after: usize,
+    /// `after - before`.
+    pub change: i64,
+    /// Base-2 log of the ratio of relative frequencies, with 0.5 added to
+    /// each count so words found on only one side get a finite value.
+    pub log_ratio: f64,
+    /// Log-likelihood or chi-squared score, when ranked by one of them.
+    pub keyness: Option<f64>,
+}
+
+/// Compares every word found in either counter, largest difference first
+/// by the chosen measure, then by word.
+pub fn compare<'a>(
+    before: &'a WordCounter,
+    after: &'a WordCounter,
+    by: CompareBy,
+) -> Vec<WordChange<'a>> {
+    let total_before = before.total() as f64;
+    let total_after = after.total() as f64;
+
+    let words: BTreeSet<&str> = before
+        .counts()
+        .keys()
+        .chain(after.counts().keys())
+        .map(String::as_str)
+        .collect();
+
+    let mut changes: Vec<WordChange> = words
+        .into_iter()
+        .map(|word| {
+            let a = before.counts().get(word).copied().unwrap_or(0);
+            let b = after.counts().get(word).copied().unwrap_or(0);
+            let keyness = match by {
+                CompareBy::LogLikelihood => Some(log_likelihood(
+                    a as f64,
+                    b as f64,
+                    total_before,
+                    total_after,
+                )),
+                CompareBy::ChiSquared => {
+                    Some(chi_squared(a as f64, b as f64, total_before, total_after))
+                }
+                CompareBy::Change | CompareBy::Relative => None,
+            };
+
+            WordChange {
+                word,
+                before: a,
+                after: b,
+                change: b as i64 - a as i64,
+                log_ratio: log_ratio(a as f64, b as f64, total_before, total_after),
+                keyness,
+            }
+        })
+        .collect();
+
+    let score = |c: &WordChange| match by {
+        CompareBy::Change => c.change.unsigned_abs() as f64,
+        CompareBy::Relative => c.log_ratio.abs(),
+        CompareBy::LogLikelihood | CompareBy::ChiSquared => c.keyness.unwrap_or(0.0),
+    };
+    changes.sort_by(|x, y| {
+        score(y)
+            .partial_cmp(&score(x))
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| x.word.cmp(y.word))
+    });
+
+    changes
+}
+
+fn log_ratio(a: f64, b: f64, total_a: f64, total_b: f64) -> f64 {
+    ((b + 0.5) / (total_b + 0.5) / ((a + 0.5) / (total_a + 0.5))).log2()
+}
+
+// Rayson & Garside's two-corpus form, with 0 * ln(0) taken as 0.
+fn log_likelihood(a: f64, b: f64, total_a: f64, total_b: f64) -> f64 {
+    let expected_a = total_a * (a + b) / (total_a + total_b);
+    let expected_b = total_b * (a + b) / (total_a + total_b);
+
+    let term = |observed: f64, expected: f64| {
+        if observed > 0.0 {
+            observed * (observed / expected).ln()
+        } else {
+            0.0
+        }
+    };
+    2.0 * (term(a, expected_a) + term(b, expected_b))
+}
+
+fn chi_squared(a: f64, b: f64, total_a: f64, total_b: f64) -> f64 {
+    let n = total_a + total_b;
+    let rest = n - a - b;
+    let denominator = (a + b) * rest * total_a * total_b;
+    if denominator == 0.0 {
+        return 0.0;
+    }
+    let cross = a * (total_b - b) - b * (total_a - a);
+    n * cross * cross / denominator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Options;
+
+    fn counter(text: &str) -> WordCounter {
+        let mut counter = WordCounter::new(&Options::new());
+        counter.feed(text);
+        counter.finish();
+        counter
+    }
+
+    #[test]
+    fn includes_words_from_either_side() {
+        let before = counter("a a b");
+        let after = counter("a c c c");
+        let changes = compare(&before, &after, CompareBy::Change);
+
+        let summary: Vec<(&str, usize, usize, i64)> = changes
+            .iter()
+            .map(|c| (c.word, c.before, c.after, c.change))
+            .collect();
+        assert_eq!(summary, [("c", 0, 3, 3), ("a", 2, 1, -1), ("b", 1, 0, -1)]);
+    }
+
+    #[test]
+    fn keyness_is_zero_for_equal_frequencies() {
+        let before = counter("a b");
+        let after = counter("a a b b");
+        for by in [CompareBy::LogLikelihood, CompareBy::ChiSquared] {
+            for change in compare(&before, &after, by) {
+                assert!(change.keyness.unwrap().abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn log_likelihood_matches_reference_value() {
+        // 10 of 1000 tokens against 30 of 1000 tokens.
+        let score = log_likelihood(10.0, 30.0, 1000.0, 1000.0);
+        assert!((score - 10.465).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,843 @@
+use std::borrow::Cow;
+use std::collections::{hash_map, HashMap, HashSet, VecDeque};
+use std::io::{self, Read};
+use std::mem;
+use std::str;
+use std::sync::Arc;
+
+use regex::RegexSet;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::normalize::Normalizer;
+use crate::tokenizer::Tokenizer;
+use crate::topk::SpaceSaving;
+use crate::Options;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// What a counter counts: words, or the characters, grapheme clusters or
+/// raw bytes of the input.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Unit {
+    Word,
+    Char,
+    Grapheme,
+    Byte,
+}
+
+impl Unit {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "word" => Some(Unit::Word),
+            "char" => Some(Unit::Char),
+            "grapheme" => Some(Unit::Grapheme),
+            "byte" => Some(Unit::Byte),
+            _ => None,
+        }
+    }
+}
+
+/// Cuts a stream of text into segments that can be counted independently,
+/// e.g. by different threads, with the same result as counting it whole.
+pub struct Segmenter {
+    unit: Unit,
+    tokenizer: Tokenizer,
+    normalizing: bool,
+    pending: String,
+}
+
+impl Segmenter {
+    pub fn new(options: &Options) -> Self {
+        Self {
+            unit: options.unit,
+            tokenizer: options.tokenizer.clone(),
+            normalizing: Normalizer::new(options).is_active(),
+            pending: String::new(),
+        }
+    }
+
+    /// Returns the text up to the last safe boundary; the rest waits for the
+    /// next chunk so words spanning reads stay whole.
+    pub fn push(&mut self, chunk: &str) -> Option<String> {
+        let offset = self.pending.len();
+        self.pending.push_str(chunk);
+
+        let cut = self.find_cut(offset).filter(|&cut| cut > 0)?;
+        let rest = self.pending.split_off(cut);
+        Some(mem::replace(&mut self.pending, rest))
+    }
+
+    // Looks for the last cut in the text added since `offset`. When the text
+    // is normalized the cut goes after whitespace, because lowercasing (final
+    // sigma) and composition look at neighbouring characters across
+    // separators but never across whitespace. Grapheme clusters can swallow
+    // combining marks after a space, so for them the cut goes before a
+    // whitespace character that starts a new cluster instead.
+    fn find_cut(&self, offset: usize) -> Option<usize> {
+        let added = &self.pending[offset..];
+
+        match self.unit {
+            Unit::Grapheme => added
+                .char_indices()
+                .rev()
+                .map(|(idx, c)| (offset + idx, c))
+                .find(|&(idx, c)| {
+                    c.is_whitespace()
+                        && match self.pending[..idx].chars().next_back() {
+                            Some(prev) => !(is_prepend(prev) || prev == '\r' && c == '\n'),
+                            None => false,
+                        }
+                })
+                .map(|(idx, _)| idx),
+            Unit::Char | Unit::Byte if !self.normalizing => Some(self.pending.len()),
+            _ => added
+                .char_indices()
+                .rev()
+                .find(|&(_, c)| {
+                    self.tokenizer.is_chunk_boundary(c) && (!self.normalizing || c.is_whitespace())
+                })
+                .map(|(idx, c)| offset + idx + c.len_utf8()),
+        }
+    }
+
+    /// Returns whatever is left once the input has ended.
+    pub fn finish(&mut self) -> String {
+        mem::take(&mut self.pending)
+    }
+}
+
+// Grapheme_Cluster_Break=Prepend, the only characters that join with a
+// following space into one cluster.
+fn is_prepend(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0600}'..='\u{0605}'
+            | '\u{06DD}'
+            | '\u{070F}'
+            | '\u{0890}'..='\u{0891}'
+            | '\u{08E2}'
+            | '\u{0D4E}'
+            | '\u{110BD}'
+            | '\u{110CD}'
+            | '\u{111C2}'..='\u{111C3}'
+            | '\u{1193F}'
+            | '\u{11941}'
+            | '\u{11A3A}'
+            | '\u{11A84}'..='\u{11A89}'
+            | '\u{11D46}'
+            | '\u{11F02}'
+    )
+}
+
+/// Counts words (or other units) in text fed to it in chunks of any size.
+///
+/// Call [`finish`](WordCounter::finish) once the input has ended; until then
+/// a word at the end of the last chunk may not have been counted yet.
+pub struct WordCounter {
+    unit: Unit,
+    min_length: usize,
+    normalizer: Normalizer,
+    tokenizer: Tokenizer,
+    stop_words: Arc<HashSet<String>>,
+    include: Option<RegexSet>,
+    exclude: Option<RegexSet>,
+    stemmer: Option<rust_stemmers::Stemmer>,
+    ngram: usize,
+    sentence_boundaries: bool,
+    window: VecDeque<String>,
+    // The words as written behind the stems in `window`, when surface forms
+    // are kept.
+    surface_window: VecDeque<String>,
+    segmenter: Segmenter,
+    byte_counts: Vec<usize>,
+    word_counts: HashMap<String, usize>,
+    // With a memory budget, words are counted here and `word_counts` holds
+    // a copy of the tracked counts once the counter is finished.
+    sketch: Option<SpaceSaving>,
+    // How often each counted key was written in each form, when stemming
+    // with surface forms.
+    surfaces: Option<HashMap<String, HashMap<String, usize>>>,
+    sentences: usize,
+    // Whether a word was seen since the last sentence end.
+    open_sentence: bool,
+}
+
+impl WordCounter {
+    pub fn new(options: &Options) -> Self {
+        let normalizer = Normalizer::new(options);
+        let stop_words = if normalizer.is_active() {
+            Arc::new(
+                options
+                    .stop_words
+                    .iter()
+                    .map(|word| normalizer.apply(word).into_owned())
+                    .collect(),
+            )
+        } else {
+            Arc::clone(&options.stop_words)
+        };
+
+        Self {
+            unit: options.unit,
+            min_length: options.min_length,
+            normalizer,
+            tokenizer: options.tokenizer.clone(),
+            stop_words,
+            include: options.include.clone(),
+            exclude: options.exclude.clone(),
+            stemmer: options.stemmer.map(|stemmer| stemmer.create()),
+            ngram: options.ngram,
+            sentence_boundaries: options.sentence_boundaries,
+            window: VecDeque::new(),
+            surface_window: VecDeque::new(),
+            segmenter: Segmenter::new(options),
+            byte_counts: Vec::new(),
+            word_counts: HashMap::new(),
+            sketch: options.approximate.map(SpaceSaving::new),
+            surfaces: options.surface_forms.then(HashMap::new),
+            sentences: 0,
+            open_sentence: false,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &str) {
+        if self.unit == Unit::Byte {
+            self.feed_bytes(chunk.as_bytes());
+            return;
+        }
+        if let Some(ready) = self.segmenter.push(chunk) {
+            self.count_segment(&ready);
+        }
+    }
+
+    /// Reads `reader` to the end. Text must be UTF-8 unless the counter
+    /// counts bytes.
+    pub fn feed_reader<R: Read>(&mut self, reader: R) -> io::Result<()> {
+        if self.unit == Unit::Byte {
+            read_chunks(reader, |bytes| self.feed_bytes(bytes))
+        } else {
+            read_utf8_chunks(reader, |text| self.feed(text))
+        }
+    }
+
+    pub fn feed_bytes(&mut self, bytes: &[u8]) {
+        if self.byte_counts.is_empty() {
+            self.byte_counts = vec![0; 256];
+        }
+        for &b in bytes {
+            self.byte_counts[b as usize] += 1;
+        }
+    }
+
+    pub fn finish(&mut self) {
+        let rest = self.segmenter.finish();
+        self.count_segment(&rest);
+
+        for (b, count) in mem::take(&mut self.byte_counts).into_iter().enumerate() {
+            if count > 0 {
+                self.add_count(format!("0x{:02x}", b), count);
+            }
+        }
+
+        if let Some(sketch) = &self.sketch {
+            self.word_counts = sketch.counts();
+        }
+    }
+
+    /// Adds the words of `text`, which must have been cut by a
+    /// [`Segmenter`] built with the same options; `feed` does the cutting
+    /// itself.
+    ///
+    /// Words that are filtered out (too short, stop words) and, optionally,
+    /// sentence ends break n-grams: only words that are adjacent in the text
+    /// and would be counted on their own form one. The window carries over
+    /// between segments so n-grams can span chunk boundaries.
+    pub fn count_segment(&mut self, text: &str) {
+        let text = self.normalizer.apply(text);
+
+        match self.unit {
+            Unit::Char => {
+                let mut buf = [0; 4];
+                for c in text.chars() {
+                    self.add(&escape_unit(c.encode_utf8(&mut buf)));
+                }
+                return;
+            }
+            Unit::Grapheme => {
+                for g in text.graphemes(true) {
+                    self.add(&escape_unit(g));
+                }
+                return;
+            }
+            Unit::Word | Unit::Byte => {}
+        }
+
+        let mut last_end = 0;
+
+        // The loop needs `self` mutably; a clone shares the compiled pattern.
+        let tokenizer = self.tokenizer.clone();
+        for (start, word) in tokenizer.word_indices(&text) {
+            if ends_sentence(&text[last_end..start]) {
+                self.end_sentence();
+            }
+            last_end = start + word.len();
+            self.open_sentence = true;
+
+            if !self.is_counted(word) {
+                self.clear_window();
+                continue;
+            }
+
+            let key = self.stem(word);
+            if self.ngram == 1 {
+                self.add_word(&key, word);
+                continue;
+            }
+
+            self.window.push_back(key.into_owned());
+            if self.surfaces.is_some() {
+                self.surface_window.push_back(word.to_string());
+            }
+            if self.window.len() > self.ngram {
+                self.window.pop_front();
+                self.surface_window.pop_front();
+            }
+            if self.window.len() == self.ngram {
+                let key: Vec<&str> = self.window.iter().map(String::as_str).collect();
+                let surface: Vec<&str> = self.surface_window.iter().map(String::as_str).collect();
+                self.add_word(&key.join(" "), &surface.join(" "));
+            }
+        }
+
+        if ends_sentence(&text[last_end..]) {
+            self.end_sentence();
+        }
+    }
+
+    /// Counts `word` as a single token without running the tokenizer. It
+    /// is still normalized and filtered like any other word.
+    pub fn count_word(&mut self, word: &str) {
+        let word = self.normalizer.apply(word);
+        if self.is_counted(&word) {
+            let key = self.stem(&word);
+            self.add_word(&key, &word);
+        }
+    }
+
+    // The key a single word is counted under, or `None` if it is filtered
+    // out.
+    pub(crate) fn key<'w>(&self, word: &'w str) -> Option<Cow<'w, str>> {
+        match self.normalizer.apply(word) {
+            Cow::Borrowed(word) => self.normalized_key(word),
+            Cow::Owned(word) => self
+                .normalized_key(&word)
+                .map(|key| Cow::Owned(key.into_owned())),
+        }
+    }
+
+    // Like `key`, for a word of text that was already normalized.
+    pub(crate) fn normalized_key<'w>(&self, word: &'w str) -> Option<Cow<'w, str>> {
+        self.is_counted(word).then(|| self.stem(word))
+    }
+
+    pub(crate) fn normalize<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        self.normalizer.apply(text)
+    }
+
+    pub(crate) fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn stem<'w>(&self, word: &'w str) -> Cow<'w, str> {
+        match &self.stemmer {
+            Some(stemmer) => stemmer.stem(word),
+            None => Cow::Borrowed(word),
+        }
+    }
+
+    fn is_counted(&self, word: &str) -> bool {
+        self.tokenizer.word_length(word) >= self.min_length
+            && !self.stop_words.contains(word)
+            && self.include.as_ref().is_none_or(|set| set.is_match(word))
+            && !self.exclude.as_ref().is_some_and(|set| set.is_match(word))
+    }
+
+    fn end_sentence(&mut self) {
+        if self.open_sentence {
+            self.sentences += 1;
+            self.open_sentence = false;
+        }
+        if self.sentence_boundaries {
+            self.clear_window();
+        }
+    }
+
+    fn clear_window(&mut self) {
+        self.window.clear();
+        self.surface_window.clear();
+    }
+
+    pub(crate) fn add_count(&mut self, key: String, count: usize) {
+        match &mut self.sketch {
+            Some(sketch) => sketch.add(&key, count),
+            None => *self.word_counts.entry(key).or_insert(0) += count,
+        }
+    }
+
+    // Counts `key`, a word or n-gram, which was written as `surface`.
+    fn add_word(&mut self, key: &str, surface: &str) {
+        self.add(key);
+        if let Some(surfaces) = &mut self.surfaces {
+            let forms = match surfaces.get_mut(key) {
+                Some(forms) => forms,
+                None => surfaces.entry(key.to_string()).or_default(),
+            };
+            match forms.get_mut(surface) {
+                Some(count) => *count += 1,
+                None => {
+                    forms.insert(surface.to_string(), 1);
+                }
+            }
+        }
+    }
+
+    fn add(&mut self, key: &str) {
+        if let Some(sketch) = &mut self.sketch {
+            sketch.add(key, 1);
+            return;
+        }
+        match self.word_counts.get_mut(key) {
+            Some(count) => *count += 1,
+            None => {
+                self.word_counts.insert(key.to_string(), 1);
+            }
+        }
+    }
+
+    /// Adds the counts of `other`, which should have been built with the
+    /// same options and finished.
+    pub fn merge(&mut self, other: WordCounter) {
+        self.sentences += other.sentence_count();
+        if let Some(other) = &other.surfaces {
+            self.add_surfaces(other);
+        }
+        if let (Some(sketch), Some(other)) = (&mut self.sketch, other.sketch) {
+            sketch.merge(other);
+            self.word_counts = sketch.counts();
+            return;
+        }
+        if self.word_counts.is_empty() {
+            self.word_counts = other.word_counts;
+            return;
+        }
+        for (word, count) in other.word_counts {
+            *self.word_counts.entry(word).or_insert(0) += count;
+        }
+    }
+
+    /// Adds the counts of a finished `other` without consuming it.
+    pub fn add_counts(&mut self, other: &WordCounter) {
+        self.sentences += other.sentence_count();
+        for (word, &count) in &other.word_counts {
+            self.add_count(word.clone(), count);
+        }
+        if let Some(other) = &other.surfaces {
+            self.add_surfaces(other);
+        }
+    }
+
+    fn add_surfaces(&mut self, other: &HashMap<String, HashMap<String, usize>>) {
+        let Some(surfaces) = &mut self.surfaces else {
+            return;
+        };
+        for (key, forms) in other {
+            let entry = surfaces.entry(key.clone()).or_default();
+            for (form, &count) in forms {
+                *entry.entry(form.clone()).or_insert(0) += count;
+            }
+        }
+    }
+
+    /// Takes back counts added earlier from `other`, forgetting words whose
+    /// count drops to zero. Together with [`add_counts`](Self::add_counts)
+    /// this keeps a sliding window. Approximate counts cannot be taken back.
+    pub fn subtract_counts(&mut self, other: &WordCounter) {
+        self.sentences = self.sentences.saturating_sub(other.sentence_count());
+        for (word, &count) in &other.word_counts {
+            if let Some(current) = self.word_counts.get_mut(word) {
+                if *current > count {
+                    *current -= count;
+                } else {
+                    self.word_counts.remove(word);
+                }
+            }
+        }
+        if let (Some(surfaces), Some(other)) = (&mut self.surfaces, &other.surfaces) {
+            for (key, forms) in other {
+                if let Some(current) = surfaces.get_mut(key) {
+                    for (form, &count) in forms {
+                        if let Some(n) = current.get_mut(form) {
+                            *n = n.saturating_sub(count);
+                        }
+                    }
+                    current.retain(|_, n| *n > 0);
+                    if current.is_empty() {
+                        surfaces.remove(key);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+
+    /// Number of sentences in the text counted so far: runs of words
+    /// ended by `.`, `!`, `?` or similar, or by the end of the text. Only
+    /// known when counting words.
+    pub fn sentences(&self) -> Option<usize> {
+        (self.unit == Unit::Word).then(|| self.sentence_count())
+    }
+
+    fn sentence_count(&self) -> usize {
+        self.sentences + self.open_sentence as usize
+    }
+
+    pub(crate) fn set_sentences(&mut self, sentences: usize) {
+        self.sentences = sentences;
+        self.open_sentence = false;
+    }
+
+    pub fn counts(&self) -> &HashMap<String, usize> {
+        &self.word_counts
+    }
+
+    pub fn into_counts(self) -> HashMap<String, usize> {
+        self.word_counts
+    }
+
+    /// Number of counted words, including the ones an approximate counter
+    /// no longer tracks.
+    pub fn total(&self) -> usize {
+        match &self.sketch {
+            Some(sketch) => sketch.total(),
+            None => self.word_counts.values().sum(),
+        }
+    }
+
+    /// Whether counts are estimates kept within a memory budget.
+    pub fn is_approximate(&self) -> bool {
+        self.sketch.is_some()
+    }
+
+    /// How much the count of `word` may exceed its true count. Always 0
+    /// for exact counters.
+    pub fn error(&self, word: &str) -> usize {
+        self.sketch.as_ref().map_or(0, |sketch| sketch.error(word))
+    }
+
+    /// The form `key` was most often written in before it was stemmed,
+    /// if the counter keeps surface forms. Ties go to the form that sorts
+    /// first.
+    pub fn surface_form(&self, key: &str) -> Option<&str> {
+        let forms = self.surfaces.as_ref()?.get(key)?;
+        forms
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(form, _)| form.as_str())
+    }
+
+    pub fn distinct(&self) -> usize {
+        self.word_counts.len()
+    }
+
+    /// Iterates the counted words by count, highest first, then by word.
+    pub fn ranked(&self) -> Ranked<'_> {
+        Ranked::new(self.word_counts.iter())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RankedWord<'a> {
+    /// Position in the ranking, starting at 1. Ties are broken by word, so
+    /// every word has its own rank.
+    pub rank: usize,
+    pub word: &'a str,
+    pub count: usize,
+}
+
+pub struct Ranked<'a> {
+    words: std::vec::IntoIter<(&'a str, usize)>,
+    rank: usize,
+}
+
+impl<'a> Ranked<'a> {
+    fn new(counts: hash_map::Iter<'a, String, usize>) -> Self {
+        let mut words: Vec<(&str, usize)> = counts.map(|(w, c)| (w.as_str(), *c)).collect();
+        words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+        Self {
+            words: words.into_iter(),
+            rank: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for Ranked<'a> {
+    type Item = RankedWord<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (word, count) = self.words.next()?;
+        self.rank += 1;
+        Some(RankedWord {
+            rank: self.rank,
+            word,
+            count,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.words.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Ranked<'_> {}
+
+// Characters and clusters that would be invisible or break the output
+// lines are shown by code point, e.g. U+000A for a newline.
+fn escape_unit(unit: &str) -> Cow<'_, str> {
+    let invisible = |c: char| {
+        c.is_control()
+            || c.is_whitespace()
+            || matches!(c, '\u{200B}'..='\u{200F}' | '\u{2060}' | '\u{FEFF}')
+    };
+
+    if !unit.contains(invisible) {
+        return Cow::Borrowed(unit);
+    }
+
+    let mut escaped = String::new();
+    for c in unit.chars() {
+        if invisible(c) {
+            escaped.push_str(&format!("U+{:04X}", c as u32));
+        } else {
+            escaped.push(c);
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+pub(crate) fn ends_sentence(gap: &str) -> bool {
+    gap.contains(['.', '!', '?', '…', '。', '！', '？'])
+}
+
+/// Calls `f` with successive chunks read from `reader`.
+pub fn read_chunks<R: Read, F: FnMut(&[u8])>(mut reader: R, mut f: F) -> io::Result<()> {
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(n) => f(&buffer[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Calls `f` with successive chunks of text read from `reader`. A UTF-8
+/// sequence cut by a read is completed before it is passed on.
+pub fn read_utf8_chunks<R: Read, F: FnMut(&str)>(mut reader: R, mut f: F) -> io::Result<()> {
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut start = 0;
+
+    loop {
+        let n = match reader.read(&mut buffer[start..]) {
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        if n == 0 {
+            if start > 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "stream did not contain valid UTF-8",
+                ));
+            }
+            return Ok(());
+        }
+
+        let end = start + n;
+        match str::from_utf8(&buffer[..end]) {
+            Ok(text) => {
+                f(text);
+                start = 0;
+            }
+            Err(e) => {
+                if e.error_len().is_some() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "stream did not contain valid UTF-8",
+                    ));
+                }
+                // A multi-byte sequence was cut by the read; keep its bytes
+                // at the front of the buffer for the next round.
+                let valid = e.valid_up_to();
+                if let Ok(text) = str::from_utf8(&buffer[..valid]) {
+                    f(text);
+                }
+                buffer.copy_within(valid..end, 0);
+                start = end - valid;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Stemmer;
+
+    fn count(options: &Options, chunks: &[&str]) -> WordCounter {
+        let mut counter = WordCounter::new(options);
+        for chunk in chunks {
+            counter.feed(chunk);
+        }
+        counter.finish();
+        counter
+    }
+
+    fn ranked(counter: &WordCounter) -> Vec<(&str, usize)> {
+        counter.ranked().map(|e| (e.word, e.count)).collect()
+    }
+
+    #[test]
+    fn ranks_by_count_then_word() {
+        let counter = count(&Options::new(), &["b a c b c d"]);
+        assert_eq!(ranked(&counter), [("b", 2), ("c", 2), ("a", 1), ("d", 1)]);
+
+        let ranks: Vec<usize> = counter.ranked().map(|e| e.rank).collect();
+        assert_eq!(ranks, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn words_split_across_chunks_are_counted_once() {
+        let options = Options::new().ignore_case(true);
+        let whole = count(&options, &["Hello world, hello WORLD"]);
+        let split = count(&options, &["Hel", "lo wo", "rld, h", "ello WOR", "LD"]);
+        assert_eq!(ranked(&whole), ranked(&split));
+        assert_eq!(ranked(&split), [("hello", 2), ("world", 2)]);
+    }
+
+    #[test]
+    fn reader_handles_utf8_split_across_reads() {
+        struct OneByte<'a>(&'a [u8]);
+
+        impl Read for OneByte<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.0.is_empty() || buf.is_empty() {
+                    return Ok(0);
+                }
+                buf[0] = self.0[0];
+                self.0 = &self.0[1..];
+                Ok(1)
+            }
+        }
+
+        let mut counter = WordCounter::new(&Options::new());
+        counter
+            .feed_reader(OneByte("héllo wörld héllo".as_bytes()))
+            .unwrap();
+        counter.finish();
+        assert_eq!(ranked(&counter), [("héllo", 2), ("wörld", 1)]);
+    }
+
+    #[test]
+    fn reader_rejects_invalid_utf8() {
+        let mut counter = WordCounter::new(&Options::new());
+        let err = counter.feed_reader(&b"ok \xff"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn merge_adds_counts() {
+        let options = Options::new();
+        let mut total = count(&options, &["a b"]);
+        total.merge(count(&options, &["b c"]));
+        assert_eq!(ranked(&total), [("b", 2), ("a", 1), ("c", 1)]);
+        assert_eq!(total.total(), 4);
+        assert_eq!(total.distinct(), 3);
+    }
+
+    #[test]
+    fn subtracted_counts_leave_the_window() {
+        let options = Options::new();
+        let first = count(&options, &["a b"]);
+        let second = count(&options, &["b c"]);
+        let mut window = WordCounter::new(&options);
+        window.add_counts(&first);
+        window.add_counts(&second);
+        window.subtract_counts(&first);
+        assert_eq!(ranked(&window), [("b", 1), ("c", 1)]);
+    }
+
+    #[test]
+    fn stop_words_are_matched_after_case_handling() {
+        let options = Options::new()
+            .ignore_case(true)
+            .stop_words(["The".to_string()]);
+        let counter = count(&options, &["The cat and the hat"]);
+        assert_eq!(ranked(&counter), [("and", 1), ("cat", 1), ("hat", 1)]);
+    }
+
+    #[test]
+    fn stems_are_counted_with_their_most_frequent_form() {
+        let options = Options::new()
+            .stemmer(Some(Stemmer::English))
+            .surface_forms(true)
+            .stop_words(["the".to_string()]);
+        let mut counter = count(&options, &["runs the running", " run running"]);
+        counter.merge(count(&options, &["runs"]));
+        assert_eq!(ranked(&counter), [("run", 5)]);
+        assert_eq!(counter.surface_form("run"), Some("running"));
+    }
+
+    #[test]
+    fn ngrams_do_not_cross_filtered_words_or_sentences() {
+        let options = Options::new()
+            .ngram(2)
+            .min_length(2)
+            .sentence_boundaries(true);
+        let counter = count(&options, &["big red a dog. red dog"]);
+        assert_eq!(ranked(&counter), [("big red", 1), ("red dog", 1)]);
+    }
+
+    #[test]
+    fn segments_can_be_counted_separately() {
+        let options = Options::new().unit(Unit::Grapheme);
+        let text = "e\u{301} \u{301}x a\r\nb";
+
+        let mut segmenter = Segmenter::new(&options);
+        let mut total = WordCounter::new(&options);
+        for piece in ["e", "\u{301} ", "\u{301}x a\r", "\nb"] {
+            if let Some(segment) = segmenter.push(piece) {
+                let mut counter = WordCounter::new(&options);
+                counter.count_segment(&segment);
+                total.merge(counter);
+            }
+        }
+        let mut counter = WordCounter::new(&options);
+        counter.count_segment(&segmenter.finish());
+        total.merge(counter);
+
+        assert_eq!(ranked(&total), ranked(&count(&options, &[text])));
+    }
+
+    #[test]
+    fn bytes_are_shown_as_hex() {
+        let counter = count(&Options::new().unit(Unit::Byte), &["aab"]);
+        assert_eq!(ranked(&counter), [("0x61", 2), ("0x62", 1)]);
+    }
+}
@@ -0,0 +1,325 @@
+use std::io::{self, Read};
+use std::str;
+
+use encoding_rs::{Decoder, DecoderResult};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// Bytes looked at to guess the encoding of input without a byte order
+// mark.
+const SAMPLE_SIZE: usize = 4096;
+
+/// Character encoding of input text.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Encoding(&'static encoding_rs::Encoding);
+
+impl Encoding {
+    pub const UTF_8: Encoding = Encoding(encoding_rs::UTF_8);
+
+    /// Accepts the usual names and aliases, like `utf-8`, `utf-16le`,
+    /// `latin1` or `shift_jis`.
+    pub fn parse(s: &str) -> Option<Self> {
+        encoding_rs::Encoding::for_label(s.as_bytes()).map(Encoding)
+    }
+
+    pub fn name(self) -> &'static str {
+        self.0.name()
+    }
+
+    // Guesses the encoding from the start of the input: a byte order mark,
+    // else UTF-8 if the sample is valid and has no zero bytes. Otherwise
+    // the likely ones of UTF-8, UTF-16 and Windows-1252 (a superset of
+    // Latin-1) are tried, and the one that decodes the sample with the
+    // fewest unlikely characters wins.
+    // `complete` tells whether the sample is the whole input.
+    fn detect(sample: &[u8], complete: bool) -> Self {
+        if let Some((encoding, _)) = encoding_rs::Encoding::for_bom(sample) {
+            return Encoding(encoding);
+        }
+
+        let sample = &sample[..sample.len().min(SAMPLE_SIZE)];
+        let utf8 = match str::from_utf8(sample) {
+            Ok(_) => true,
+            // A sequence cut at the end of the sample.
+            Err(e) => e.error_len().is_none() && !complete,
+        };
+        if utf8 && !sample.contains(&0) {
+            return Encoding::UTF_8;
+        }
+
+        // Mostly-ASCII UTF-16 has a zero in every other byte: in more than
+        // half of the bytes on one side and hardly any on the other.
+        let zeros = |parity: usize| {
+            sample
+                .iter()
+                .skip(parity)
+                .step_by(2)
+                .filter(|&&b| b == 0)
+                .count()
+        };
+        let (even, odd) = (zeros(0), zeros(1));
+        let half = sample.len() / 2;
+        let mut candidates = Vec::new();
+        if odd * 2 > half && even * 8 < half {
+            candidates.push(encoding_rs::UTF_16LE);
+        } else if even * 2 > half && odd * 8 < half {
+            candidates.push(encoding_rs::UTF_16BE);
+        }
+
+        // Other UTF-16 is not valid UTF-8. Its zeros, if any, are on the
+        // side of the byte order; without zeros it has to decode better
+        // than Windows-1252 to be chosen.
+        if utf8 {
+            candidates.push(encoding_rs::UTF_8);
+        } else if even + odd > 0 {
+            if odd >= even {
+                candidates.push(encoding_rs::UTF_16LE);
+            }
+            if even >= odd {
+                candidates.push(encoding_rs::UTF_16BE);
+            }
+            candidates.push(encoding_rs::WINDOWS_1252);
+        } else {
+            candidates.extend([
+                encoding_rs::WINDOWS_1252,
+                encoding_rs::UTF_16LE,
+                encoding_rs::UTF_16BE,
+            ]);
+        }
+
+        candidates
+            .into_iter()
+            .filter_map(|encoding| {
+                let text = decode_sample(encoding, sample, complete)?;
+                Some((text.chars().filter(|&c| unlikely(c)).count(), encoding))
+            })
+            // The first of the best, so ties go to the more likely encoding.
+            .min_by_key(|&(unlikely, _)| unlikely)
+            .map_or(Encoding(encoding_rs::WINDOWS_1252), |(_, encoding)| {
+                Encoding(encoding)
+            })
+    }
+}
+
+fn decode_sample(
+    encoding: &'static encoding_rs::Encoding,
+    sample: &[u8],
+    complete: bool,
+) -> Option<String> {
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut text =
+        String::with_capacity(decoder.max_utf8_buffer_length_without_replacement(sample.len())?);
+    let (result, _) = decoder.decode_to_string_without_replacement(sample, &mut text, complete);
+    (result == DecoderResult::InputEmpty).then_some(text)
+}
+
+// Characters text rarely holds but a wrong guess often produces.
+fn unlikely(c: char) -> bool {
+    (c.is_control() && !c.is_whitespace())
+        || ('\u{E000}'..='\u{F8FF}').contains(&c)
+        || c == '\u{FFFD}'
+        || c as u32 & 0xFFFE == 0xFFFE
+}
+
+/// Reads text in any [`Encoding`] as UTF-8, ready for
+/// [`WordCounter::feed_reader`](crate::WordCounter::feed_reader).
+///
+/// A byte order mark is removed. Without an explicit encoding, one is
+/// detected from the start of the input. Malformed input is an error,
+/// unless the reader is lossy: then each malformed sequence becomes
+/// U+FFFD and is counted in [`replacements`](TextReader::replacements).
+pub struct TextReader<R> {
+    inner: R,
+    requested: Option<Encoding>,
+    lossy: bool,
+    decoder: Option<(Encoding, Decoder)>,
+    guessed: bool,
+    raw: Vec<u8>,
+    eof: bool,
+    // Decoded text not yet returned by `read`.
+    text: String,
+    text_start: usize,
+    position: u64,
+    replacements: usize,
+}
+
+impl<R> TextReader<R> {
+    pub fn new(inner: R, encoding: Option<Encoding>, lossy: bool) -> Self {
+        Self {
+            inner,
+            requested: encoding,
+            lossy,
+            decoder: None,
+            guessed: false,
+            raw: Vec::new(),
+            eof: false,
+            text: String::new(),
+            text_start: 0,
+            position: 0,
+            replacements: 0,
+        }
+    }
+
+    /// The encoding being read, once reading has started.
+    pub fn encoding(&self) -> Option<Encoding> {
+        self.decoder.as_ref().map(|(encoding, _)| *encoding)
+    }
+
+    /// The encoding read, if it is not UTF-8 and was guessed from the
+    /// content rather than given or marked by a byte order mark.
+    pub fn guessed(&self) -> Option<Encoding> {
+        self.encoding().filter(|_| self.guessed)
+    }
+
+    /// Number of malformed sequences replaced so far.
+    pub fn replacements(&self) -> usize {
+        self.replacements
+    }
+}
+
+impl<R: Read> TextReader<R> {
+    // Reads until `raw` holds at least `n` bytes or the input has ended.
+    fn fill(&mut self, n: usize) -> io::Result<()> {
+        let mut buf = [0; CHUNK_SIZE];
+        while self.raw.len() < n && !self.eof {
+            match self.inner.read(&mut buf) {
+                Ok(0) => self.eof = true,
+                Ok(read) => self.raw.extend_from_slice(&buf[..read]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_more(&mut self) -> io::Result<()> {
+        if self.decoder.is_none() {
+            self.fill(SAMPLE_SIZE)?;
+            let decoder = match self.requested {
+                Some(encoding) => (encoding, encoding.0.new_decoder_with_bom_removal()),
+                None => {
+                    let encoding = Encoding::detect(&self.raw, self.eof);
+                    self.guessed = encoding != Encoding::UTF_8
+                        && encoding_rs::Encoding::for_bom(&self.raw).is_none();
+                    (encoding, encoding.0.new_decoder())
+                }
+            };
+            self.decoder = Some(decoder);
+        } else {
+            self.fill(1)?;
+        }
+
+        let (encoding, decoder) = self.decoder.as_mut().unwrap();
+        self.text.clear();
+        self.text_start = 0;
+        self.text.reserve(
+            decoder
+                .max_utf8_buffer_length_without_replacement(self.raw.len())
+                .unwrap_or(self.raw.len()),
+        );
+
+        let mut read = 0;
+        loop {
+            let (result, n) = decoder.decode_to_string_without_replacement(
+                &self.raw[read..],
+                &mut self.text,
+                self.eof,
+            );
+            read += n;
+            match result {
+                DecoderResult::InputEmpty => break,
+                DecoderResult::OutputFull => self.text.reserve(CHUNK_SIZE),
+                DecoderResult::Malformed(bad, extra) => {
+                    if !self.lossy {
+                        let start = read.saturating_sub(extra as usize + bad as usize);
+                        let offset = self.position + start as u64;
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("invalid {} at byte {}", encoding.name(), offset),
+                        ));
+                    }
+                    self.text.push('\u{FFFD}');
+                    self.replacements += 1;
+                }
+            }
+        }
+        self.raw.drain(..read);
+        self.position += read as u64;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for TextReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.text_start == self.text.len() {
+            if self.eof && self.raw.is_empty() && self.decoder.is_some() {
+                return Ok(0);
+            }
+            self.decode_more()?;
+        }
+
+        let pending = &self.text.as_bytes()[self.text_start..];
+        let n = pending.len().min(buf.len());
+        buf[..n].copy_from_slice(&pending[..n]);
+        self.text_start += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(bytes: &[u8], encoding: Option<Encoding>, lossy: bool) -> io::Result<(String, usize)> {
+        let mut reader = TextReader::new(bytes, encoding, lossy);
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Ok((text, reader.replacements()))
+    }
+
+    #[test]
+    fn detects_boms_utf16_and_latin1() {
+        let utf16: Vec<u8> = "héllo wörld"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(read(&utf16, None, false).unwrap().0, "héllo wörld");
+        assert_eq!(read(b"\xef\xbb\xbfhi", None, false).unwrap().0, "hi");
+        assert_eq!(read(b"h\0i\0 \0h\0i\0", None, false).unwrap().0, "hi hi");
+        assert_eq!(read(b"\0h\0i", None, false).unwrap().0, "hi");
+        assert_eq!(read(b"caf\xe9", None, false).unwrap().0, "café");
+        assert_eq!(
+            read(b"caf\xe9", Encoding::parse("latin1"), false)
+                .unwrap()
+                .0,
+            "café"
+        );
+    }
+
+    #[test]
+    fn detects_utf16_without_ascii() {
+        for text in ["日本語 テスト", "Ελληνικά και Русский"] {
+            let le: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+            let be: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+            assert_eq!(read(&le, None, false).unwrap().0, text);
+            assert_eq!(read(&be, None, false).unwrap().0, text);
+        }
+
+        let mut reader = TextReader::new(&b"caf\xe9"[..], None, false);
+        reader.read_to_string(&mut String::new()).unwrap();
+        assert_eq!(reader.guessed().map(Encoding::name), Some("windows-1252"));
+        let mut reader = TextReader::new(&b"\xff\xfeh\0"[..], None, false);
+        reader.read_to_string(&mut String::new()).unwrap();
+        assert!(reader.guessed().is_none());
+    }
+
+    #[test]
+    fn malformed_input_fails_or_is_replaced() {
+        let err = read(b"ok \xff", Some(Encoding::UTF_8), false).unwrap_err();
+        assert_eq!(err.to_string(), "invalid UTF-8 at byte 3");
+
+        let (text, replaced) = read(b"a\xffb\xfe", Some(Encoding::UTF_8), true).unwrap();
+        assert_eq!(text, "a\u{FFFD}b\u{FFFD}");
+        assert_eq!(replaced, 2);
+    }
+}
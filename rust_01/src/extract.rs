@@ -0,0 +1,412 @@
+use std::io::{self, BufReader, Read};
+
+use pulldown_cmark::{Event, Parser, TagEnd};
+use serde_json::Value;
+
+/// The part of structured input that is counted, leaving out markup, keys
+/// and other columns.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Extract {
+    /// The strings at any of these paths of each JSON document. Input may
+    /// be a single document or a stream of them, like JSON Lines.
+    Json(Vec<JsonPath>),
+    /// The fields of the chosen columns of each CSV row.
+    Csv {
+        columns: Vec<Column>,
+        delimiter: u8,
+        /// Whether the first row names the columns rather than holding data.
+        header: bool,
+    },
+    /// The text of an HTML page, see [`html_text`].
+    Html,
+    /// The text of a Markdown document, see [`markdown_text`].
+    Markdown,
+}
+
+/// A path into a JSON document: object keys and array indexes separated
+/// by dots, with `*` standing for every key or element, like `user.name`
+/// or `items.*.text`. `.` alone is the whole document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonPath(Vec<String>);
+
+impl JsonPath {
+    pub fn parse(s: &str) -> Self {
+        JsonPath(
+            s.split('.')
+                .filter(|key| !key.is_empty())
+                .map(str::to_string)
+                .collect(),
+        )
+    }
+}
+
+/// A CSV column, by its number counting from 1 or by its name in the
+/// header row.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Column {
+    Number(usize),
+    Name(String),
+}
+
+impl Column {
+    /// Digits are a column number, anything else a name.
+    pub fn parse(s: &str) -> Option<Self> {
+        if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+            s.parse().ok().filter(|&n| n > 0).map(Column::Number)
+        } else {
+            Some(Column::Name(s.to_string()))
+        }
+    }
+}
+
+impl Extract {
+    /// Reads `reader`, which must be UTF-8, to the end and calls `f` with
+    /// each piece of text to count: a JSON string, a CSV field, or the
+    /// text of a whole HTML or Markdown document.
+    pub fn read<R: Read, F: FnMut(&str)>(&self, mut reader: R, mut f: F) -> io::Result<()> {
+        match self {
+            Extract::Json(paths) => {
+                let documents = serde_json::Deserializer::from_reader(BufReader::new(reader));
+                for document in documents.into_iter::<Value>() {
+                    let document = document.map_err(json_error)?;
+                    for path in paths {
+                        select(&document, &path.0, &mut f);
+                    }
+                }
+                Ok(())
+            }
+            Extract::Csv {
+                columns,
+                delimiter,
+                header,
+            } => read_csv(reader, columns, *delimiter, *header, f),
+            Extract::Html | Extract::Markdown => {
+                let mut document = String::new();
+                reader.read_to_string(&mut document)?;
+                if *self == Extract::Html {
+                    f(&html_text(&document));
+                } else {
+                    f(&markdown_text(&document));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn select<F: FnMut(&str)>(value: &Value, path: &[String], f: &mut F) {
+    let Some((key, rest)) = path.split_first() else {
+        strings(value, f);
+        return;
+    };
+    match value {
+        Value::Object(map) if key == "*" => map.values().for_each(|v| select(v, rest, f)),
+        Value::Array(items) if key == "*" => items.iter().for_each(|v| select(v, rest, f)),
+        Value::Object(map) => {
+            if let Some(v) = map.get(key) {
+                select(v, rest, f);
+            }
+        }
+        Value::Array(items) => {
+            if let Some(v) = key.parse().ok().and_then(|index: usize| items.get(index)) {
+                select(v, rest, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Every string within `value`; keys, numbers and the like are left out.
+fn strings<F: FnMut(&str)>(value: &Value, f: &mut F) {
+    match value {
+        Value::String(s) => f(s),
+        Value::Array(items) => items.iter().for_each(|v| strings(v, f)),
+        Value::Object(map) => map.values().for_each(|v| strings(v, f)),
+        _ => {}
+    }
+}
+
+// Errors of the input itself are passed on, so undecodable text is still
+// reported as invalid data.
+fn json_error(e: serde_json::Error) -> io::Error {
+    if e.is_io() {
+        e.into()
+    } else {
+        io::Error::other(format!("invalid JSON: {}", e))
+    }
+}
+
+fn read_csv<R: Read, F: FnMut(&str)>(
+    reader: R,
+    columns: &[Column],
+    delimiter: u8,
+    header: bool,
+    mut f: F,
+) -> io::Result<()> {
+    let mut csv = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(header)
+        .flexible(true)
+        .from_reader(reader);
+
+    let names = if header {
+        csv.headers().map_err(csv_error)?.clone()
+    } else {
+        csv::StringRecord::new()
+    };
+    let indexes = columns
+        .iter()
+        .map(|column| match column {
+            Column::Number(n) => Ok(n - 1),
+            Column::Name(name) => names
+                .iter()
+                .position(|field| field.trim() == name)
+                .ok_or_else(|| io::Error::other(format!("no column named {}", name))),
+        })
+        .collect::<io::Result<Vec<usize>>>()?;
+
+    let mut record = csv::StringRecord::new();
+    while csv.read_record(&mut record).map_err(csv_error)? {
+        for &index in &indexes {
+            if let Some(field) = record.get(index) {
+                f(field);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn csv_error(e: csv::Error) -> io::Error {
+    let message = e.to_string();
+    match e.into_kind() {
+        csv::ErrorKind::Io(e) => e,
+        _ => io::Error::other(format!("invalid CSV: {}", message)),
+    }
+}
+
+// Elements that start a new line of text, so the words of neighbouring
+// blocks stay apart.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "option",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "title",
+    "tr",
+    "ul",
+];
+
+// Elements whose content is not text.
+const SKIPPED_ELEMENTS: &[&str] = &["script", "style", "template"];
+
+/// The text of an HTML document or fragment. Tags, comments and the
+/// content of `script` and `style` elements are removed, and character
+/// references like `&amp;` are decoded.
+pub fn html_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        html_escape::decode_html_entities_to_string(&rest[..start], &mut text);
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        // A `<` that starts no tag is text, as in "a < b".
+        let closing = rest[1..].starts_with('/');
+        let name: String = rest[1 + closing as usize..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if name.is_empty() && !rest[1..].starts_with(['!', '?']) {
+            text.push('<');
+            rest = &rest[1..];
+            continue;
+        }
+
+        rest = tag_end(rest).map_or("", |end| &rest[end..]);
+        if BLOCK_ELEMENTS.contains(&name.as_str()) {
+            text.push('\n');
+        } else if !closing && SKIPPED_ELEMENTS.contains(&name.as_str()) {
+            rest = match find_closing_tag(rest, &name) {
+                Some(close) => tag_end(&rest[close..]).map_or("", |end| &rest[close + end..]),
+                None => "",
+            };
+        }
+    }
+    html_escape::decode_html_entities_to_string(rest, &mut text);
+    text
+}
+
+// The length of the tag `s` starts with; a `>` in a quoted attribute value
+// does not end it.
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return Some(index + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn find_closing_tag(s: &str, name: &str) -> Option<usize> {
+    s.match_indices("</")
+        .map(|(index, _)| index)
+        .find(|&index| {
+            s[index + 2..]
+                .get(..name.len())
+                .is_some_and(|tag| tag.eq_ignore_ascii_case(name))
+        })
+}
+
+/// The text of a Markdown document without its syntax: emphasis, link
+/// targets, list markers, code fences and the like are removed, and HTML in
+/// it is stripped like [`html_text`].
+pub fn markdown_text(markdown: &str) -> String {
+    let options = pulldown_cmark::Options::ENABLE_TABLES
+        | pulldown_cmark::Options::ENABLE_STRIKETHROUGH
+        | pulldown_cmark::Options::ENABLE_TASKLISTS
+        | pulldown_cmark::Options::ENABLE_FOOTNOTES;
+    let mut text = String::with_capacity(markdown.len());
+    // An HTML block comes a line at a time.
+    let mut html = String::new();
+
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Text(s) | Event::Code(s) | Event::InlineMath(s) | Event::DisplayMath(s) => {
+                text.push_str(&s)
+            }
+            Event::Html(s) => html.push_str(&s),
+            Event::InlineHtml(s) => text.push_str(&html_text(&s)),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::End(TagEnd::HtmlBlock) => {
+                text.push_str(&html_text(&html));
+                text.push('\n');
+                html.clear();
+            }
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Heading(_)
+                | TagEnd::CodeBlock
+                | TagEnd::Item
+                | TagEnd::TableCell
+                | TagEnd::FootnoteDefinition,
+            ) => text.push('\n'),
+            _ => {}
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pieces(extract: &Extract, input: &str) -> Vec<String> {
+        let mut pieces = Vec::new();
+        extract
+            .read(input.as_bytes(), |text| pieces.push(text.to_string()))
+            .unwrap();
+        pieces
+    }
+
+    #[test]
+    fn json_paths_select_strings_of_each_document() {
+        let extract = Extract::Json(vec![JsonPath::parse("msg"), JsonPath::parse("tags.*")]);
+        let logs = "{\"msg\": \"disk full\", \"level\": \"error\", \"tags\": [\"io\", 3]}\n\
+                    {\"msg\": {\"text\": \"retry\"}}\n";
+        assert_eq!(pieces(&extract, logs), ["disk full", "io", "retry"]);
+
+        let whole = Extract::Json(vec![JsonPath::parse(".")]);
+        assert_eq!(
+            pieces(&whole, "[{\"a\": \"x\"}, {\"b\": [\"y\"]}]"),
+            ["x", "y"]
+        );
+
+        let err = whole.read(&b"{\"a\": "[..], |_| {}).unwrap_err();
+        assert!(err.to_string().starts_with("invalid JSON"));
+    }
+
+    #[test]
+    fn csv_columns_by_name_or_number() {
+        let extract = Extract::Csv {
+            columns: vec![Column::Name("comment".to_string()), Column::Number(1)],
+            delimiter: b',',
+            header: true,
+        };
+        let csv = "id,comment\n1,\"fine, thanks\"\n2\n3,\"said \"\"hi\"\"\"\n";
+        assert_eq!(
+            pieces(&extract, csv),
+            ["fine, thanks", "1", "2", "said \"hi\"", "3"]
+        );
+
+        let missing = Extract::Csv {
+            columns: vec![Column::Name("body".to_string())],
+            delimiter: b',',
+            header: true,
+        };
+        let err = missing.read(csv.as_bytes(), |_| {}).unwrap_err();
+        assert_eq!(err.to_string(), "no column named body");
+        assert_eq!(Column::parse("0"), None);
+    }
+
+    #[test]
+    fn markup_is_stripped() {
+        let html = "<html><head><title>Caf&eacute;</title><style>p { x: 1 }</style></head>\
+                    <body><p class=\"a>b\">Fish &amp; chips<br>a &lt; b < c</p>\
+                    <!-- hidden --><SCRIPT>var x;</script>end</body></html>";
+        let words: Vec<String> = html_text(html)
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        assert_eq!(
+            words,
+            ["Café", "Fish", "&", "chips", "a", "<", "b", "<", "c", "end"]
+        );
+
+        let markdown = "# Title\n\nSome *emphasis* and [a link](http://example.com).\n\n\
+                        - item `code`\n\n<div>\nraw <b>html</b>\n</div>\n";
+        let words: Vec<String> = markdown_text(markdown)
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        assert_eq!(
+            words,
+            ["Title", "Some", "emphasis", "and", "a", "link.", "item", "code", "raw", "html"]
+        );
+    }
+}
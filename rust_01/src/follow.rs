@@ -0,0 +1,334 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, IsTerminal, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::process;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use wordfreq::{Options, WordCounter};
+
+use crate::input::{self, Input};
+use crate::output::{Format, Printer};
+use crate::Config;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Lines read within this span share a counter in a time window, and leave
+// the window together, at most this much early.
+const BUCKET: Duration = Duration::from_millis(100);
+
+/// Which lines the rankings of `--follow` are computed over.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Window {
+    All,
+    Lines(usize),
+    Seconds(u64),
+}
+
+// Reads the input line by line on a separate thread and redraws the
+// ranking every `config.interval`. Files are watched for new lines until
+// interrupted; stdin and texts end the loop when they are exhausted.
+pub fn run(config: &Config, input: &Input) {
+    let (sender, receiver) = mpsc::channel();
+    match input {
+        Input::Stdin => {
+            thread::spawn(move || read_stdin(sender));
+        }
+        Input::File(path) => {
+            let path = path.clone();
+            thread::spawn(move || tail_file(path, sender));
+        }
+        Input::Text(t) => {
+            for line in t.lines() {
+                let _ = sender.send(Ok(line.to_string()));
+            }
+            drop(sender);
+        }
+    }
+
+    let terminal = io::stdout().is_terminal();
+    let mut counts = Counts::new(&config.options, config.window);
+    let mut changed = false;
+    let mut next_draw = Instant::now() + config.interval;
+
+    loop {
+        let timeout = next_draw.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(timeout) {
+            Ok(Ok(line)) => {
+                counts.add(&line, Instant::now());
+                changed = true;
+            }
+            Ok(Err(e)) => {
+                eprintln!("error: {}: {}", input::label(input), e);
+                process::exit(1);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                counts.expire(Instant::now());
+                counts.draw(config, input, terminal);
+                return;
+            }
+        }
+
+        if Instant::now() >= next_draw {
+            // Outside a terminal every redraw is appended, so only print
+            // rankings that changed.
+            changed |= counts.expire(Instant::now());
+            if changed || terminal {
+                counts.draw(config, input, terminal);
+                changed = false;
+            }
+            next_draw = Instant::now() + config.interval;
+        }
+    }
+}
+
+// The counts of the current window. Windowed lines are counted on their
+// own and kept, so they can be taken back out of the total when they
+// leave the window; time windows keep one counter per `BUCKET`. The
+// current time is passed in rather than read here.
+struct Counts<'a> {
+    options: &'a Options,
+    window: Window,
+    total: WordCounter,
+    recent: VecDeque<(Instant, WordCounter)>,
+    lines: usize,
+}
+
+impl<'a> Counts<'a> {
+    fn new(options: &'a Options, window: Window) -> Self {
+        Self {
+            options,
+            window,
+            total: WordCounter::new(options),
+            recent: VecDeque::new(),
+            lines: 0,
+        }
+    }
+
+    fn add(&mut self, line: &str, now: Instant) {
+        self.lines += 1;
+
+        if self.window == Window::All {
+            self.total.feed(line);
+            self.total.feed("\n");
+            return;
+        }
+
+        let mut counter = WordCounter::new(self.options);
+        counter.feed(line);
+        counter.feed("\n");
+        counter.finish();
+        self.total.add_counts(&counter);
+
+        match self.window {
+            Window::Seconds(_) => match self.recent.back_mut() {
+                Some((start, bucket)) if now.duration_since(*start) < BUCKET => {
+                    bucket.merge(counter);
+                }
+                _ => self.recent.push_back((now, counter)),
+            },
+            Window::Lines(n) => {
+                self.recent.push_back((now, counter));
+                if self.recent.len() > n {
+                    let (_, old) = self.recent.pop_front().unwrap();
+                    self.total.subtract_counts(&old);
+                }
+            }
+            Window::All => {}
+        }
+    }
+
+    // Drops the seconds that have left a time window, returning whether
+    // anything was dropped.
+    fn expire(&mut self, now: Instant) -> bool {
+        let Window::Seconds(seconds) = self.window else {
+            return false;
+        };
+
+        let mut expired = false;
+        while let Some((start, _)) = self.recent.front() {
+            if now.duration_since(*start) < Duration::from_secs(seconds) {
+                break;
+            }
+            let (_, old) = self.recent.pop_front().unwrap();
+            self.total.subtract_counts(&old);
+            expired = true;
+        }
+        expired
+    }
+
+    fn draw(&mut self, config: &Config, input: &Input, terminal: bool) {
+        if self.window == Window::All {
+            self.total.finish();
+        }
+
+        if terminal {
+            print!("\x1b[2J\x1b[H");
+        }
+        if config.format == Format::Text {
+            let window = match self.window {
+                Window::All => "all lines".to_string(),
+                Window::Lines(n) => format!("last {} lines", n),
+                Window::Seconds(s) => format!("last {} seconds", s),
+            };
+            println!(
+                "==> {} ({} lines read, {}) <==",
+                input::label(input),
+                self.lines,
+                window
+            );
+        }
+        Printer::new(config, false).single(&self.total);
+        if !terminal {
+            println!();
+        }
+    }
+}
+
+fn read_stdin(sender: Sender<io::Result<String>>) {
+    let mut stdin = io::stdin().lock();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match stdin.read_until(b'\n', &mut line) {
+            Ok(0) => return,
+            Ok(_) => {
+                if line.last() == Some(&b'\n') {
+                    line.pop();
+                }
+                if sender.send(decode(&line)).is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                let _ = sender.send(Err(e));
+                return;
+            }
+        }
+    }
+}
+
+// Like `tail -f`: reads the whole file, then polls for appended lines. A
+// file that shrinks was truncated or rotated in place and is read again
+// from the start.
+fn tail_file(path: PathBuf, sender: Sender<io::Result<String>>) {
+    let result = (|| -> io::Result<()> {
+        let mut file = File::open(&path)?;
+        let mut position = 0;
+        let mut pending = Vec::new();
+        let mut buf = vec![0; 64 * 1024];
+
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                if file.metadata()?.len() < position {
+                    file.seek(SeekFrom::Start(0))?;
+                    position = 0;
+                    pending.clear();
+                }
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            position += n as u64;
+
+            pending.extend_from_slice(&buf[..n]);
+            let mut start = 0;
+            while let Some(end) = pending[start..].iter().position(|&b| b == b'\n') {
+                let line = decode(&pending[start..start + end]);
+                if sender.send(line).is_err() {
+                    return Ok(());
+                }
+                start += end + 1;
+            }
+            pending.drain(..start);
+        }
+    })();
+
+    if let Err(e) = result {
+        let _ = sender.send(Err(e));
+    }
+}
+
+fn decode(line: &[u8]) -> io::Result<String> {
+    String::from_utf8(line.to_vec()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranked(counts: &Counts) -> Vec<(String, usize)> {
+        counts
+            .total
+            .ranked()
+            .map(|e| (e.word.to_string(), e.count))
+            .collect()
+    }
+
+    fn words(pairs: &[(&str, usize)]) -> Vec<(String, usize)> {
+        pairs.iter().map(|&(w, n)| (w.to_string(), n)).collect()
+    }
+
+    #[test]
+    fn line_window_takes_back_the_oldest_line() {
+        let options = Options::new();
+        let mut counts = Counts::new(&options, Window::Lines(2));
+        let now = Instant::now();
+        counts.add("a b", now);
+        counts.add("b c", now);
+        assert_eq!(ranked(&counts), words(&[("b", 2), ("a", 1), ("c", 1)]));
+
+        counts.add("c d", now);
+        assert_eq!(ranked(&counts), words(&[("c", 2), ("b", 1), ("d", 1)]));
+        counts.add("d", now);
+        counts.add("d", now);
+        assert_eq!(ranked(&counts), words(&[("d", 2)]));
+        assert_eq!(counts.lines, 5);
+        assert!(!counts.expire(now + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn time_window_drops_buckets_as_they_age() {
+        let options = Options::new();
+        let mut counts = Counts::new(&options, Window::Seconds(2));
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        counts.add("a b", at(0));
+        // Within one bucket of the first line, so it leaves with it.
+        counts.add("b", at(50));
+        counts.add("b c", at(1000));
+        assert_eq!(counts.recent.len(), 2);
+        assert_eq!(ranked(&counts), words(&[("b", 3), ("a", 1), ("c", 1)]));
+
+        assert!(!counts.expire(at(1999)));
+        assert!(counts.expire(at(2000)));
+        assert_eq!(ranked(&counts), words(&[("b", 1), ("c", 1)]));
+
+        counts.add("c", at(2500));
+        assert!(counts.expire(at(3000)));
+        assert_eq!(ranked(&counts), words(&[("c", 1)]));
+        assert!(counts.expire(at(4500)));
+        assert!(ranked(&counts).is_empty());
+        assert!(!counts.expire(at(9000)));
+    }
+
+    #[test]
+    fn unbounded_window_keeps_everything() {
+        let options = Options::new();
+        let mut counts = Counts::new(&options, Window::All);
+        let now = Instant::now();
+        counts.add("a b", now);
+        counts.add("a", now);
+        assert!(!counts.expire(now + Duration::from_secs(3600)));
+        counts.total.finish();
+        assert_eq!(ranked(&counts), words(&[("a", 2), ("b", 1)]));
+    }
+}
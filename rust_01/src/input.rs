@@ -0,0 +1,376 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use flate2::read::MultiGzDecoder;
+use wordfreq::{read_utf8_chunks, Extract, TextReader};
+
+use crate::Config;
+
+pub enum Input {
+    Stdin,
+    Text(String),
+    File(PathBuf),
+}
+
+pub fn collect_inputs(args: &[String], config: &Config) -> io::Result<Vec<Input>> {
+    if args.is_empty() {
+        return Ok(vec![Input::Stdin]);
+    }
+
+    if config.literal_text {
+        return Ok(args.iter().map(|t| Input::Text(t.clone())).collect());
+    }
+
+    let mut inputs = Vec::new();
+    for arg in args {
+        if arg == "-" {
+            inputs.push(Input::Stdin);
+            continue;
+        }
+
+        let path = PathBuf::from(arg);
+        let metadata =
+            fs::metadata(&path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", arg, e)))?;
+        if metadata.is_dir() {
+            let mut files = Vec::new();
+            walk_dir(&path, &path, config, &mut files)?;
+            inputs.extend(files.into_iter().map(Input::File));
+        } else {
+            inputs.push(Input::File(path));
+        }
+    }
+
+    Ok(inputs)
+}
+
+fn walk_dir(root: &Path, dir: &Path, config: &Config, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", dir.display(), e)))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let relative = relative_path(root, &path);
+
+        if config
+            .exclude_files
+            .iter()
+            .any(|pattern| path_matches(pattern, &relative))
+        {
+            continue;
+        }
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk_dir(root, &path, config, files)?;
+        } else if path.is_file()
+            && (config.include_files.is_empty()
+                || config
+                    .include_files
+                    .iter()
+                    .any(|pattern| path_matches(pattern, &relative)))
+        {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn relative_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let parts: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    parts.join("/")
+}
+
+// Patterns without a '/' only look at the file name, like .gitignore.
+fn path_matches(pattern: &str, relative: &str) -> bool {
+    let text = if pattern.contains('/') {
+        relative
+    } else {
+        relative.rsplit('/').next().unwrap_or(relative)
+    };
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match(&pattern, &text)
+}
+
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    if pattern.is_empty() {
+        return text.is_empty();
+    }
+
+    match pattern[0] {
+        '*' if pattern.starts_with(&['*', '*']) => {
+            let rest = pattern[2..].strip_prefix(&['/']).unwrap_or(&pattern[2..]);
+            (0..=text.len()).any(|i| {
+                (i == 0 || text[i - 1] == '/') && glob_match(rest, &text[i..])
+                    || glob_match(&pattern[2..], &text[i..])
+            })
+        }
+        '*' => {
+            let mut i = 0;
+            loop {
+                if glob_match(&pattern[1..], &text[i..]) {
+                    return true;
+                }
+                if i == text.len() || text[i] == '/' {
+                    return false;
+                }
+                i += 1;
+            }
+        }
+        '?' => !text.is_empty() && text[0] != '/' && glob_match(&pattern[1..], &text[1..]),
+        '[' => match pattern.iter().position(|&c| c == ']') {
+            Some(close) if close > 1 && !text.is_empty() => {
+                let (negated, set) = match pattern[1] {
+                    '!' | '^' => (true, &pattern[2..close]),
+                    _ => (false, &pattern[1..close]),
+                };
+                let mut matched = false;
+                let mut j = 0;
+                while j < set.len() {
+                    if j + 2 < set.len() && set[j + 1] == '-' {
+                        matched |= set[j] <= text[0] && text[0] <= set[j + 2];
+                        j += 3;
+                    } else {
+                        matched |= set[j] == text[0];
+                        j += 1;
+                    }
+                }
+                matched != negated && glob_match(&pattern[close + 1..], &text[1..])
+            }
+            _ => !text.is_empty() && text[0] == '[' && glob_match(&pattern[1..], &text[1..]),
+        },
+        c => !text.is_empty() && text[0] == c && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+// Opens the raw bytes of `input`, decompressing gzip data, which is
+// recognized by its magic number rather than the file name.
+pub fn open(input: &Input) -> io::Result<Box<dyn Read + Send + '_>> {
+    let reader: Box<dyn Read + Send> = match input {
+        Input::Text(t) => return Ok(Box::new(t.as_bytes())),
+        Input::Stdin => Box::new(io::stdin()),
+        Input::File(path) => Box::new(File::open(path)?),
+    };
+
+    let mut reader = BufReader::new(reader);
+    if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        Ok(Box::new(MultiGzDecoder::new(reader)))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+// Opens `input` as UTF-8 text, decoded from the encoding `config` asks for.
+pub fn open_text<'a>(
+    input: &'a Input,
+    config: &Config,
+) -> io::Result<TextReader<Box<dyn Read + Send + 'a>>> {
+    Ok(TextReader::new(open(input)?, config.encoding, config.lossy))
+}
+
+// Warns about a guessed encoding other than UTF-8, and about the
+// malformed sequences a lossy reader replaced.
+pub fn report_decoding<R>(input: &Input, reader: &TextReader<R>) {
+    if let Some(encoding) = reader.guessed() {
+        eprintln!(
+            "warning: {}: read as {}, guessed from its content (choose the encoding with --encoding)",
+            label(input),
+            encoding.name()
+        );
+    }
+    if reader.replacements() > 0 {
+        eprintln!(
+            "warning: {}: replaced {} invalid {} sequences with U+FFFD",
+            label(input),
+            reader.replacements(),
+            reader.encoding().map_or("", |encoding| encoding.name())
+        );
+    }
+}
+
+// Calls `f` with each piece of text `extract` takes from `input`. Pieces
+// are separate, so `f` must keep words of neighbouring pieces apart.
+pub fn extract_text<F: FnMut(&str)>(
+    input: &Input,
+    extract: &Extract,
+    config: &Config,
+    f: F,
+) -> io::Result<()> {
+    if let Input::Text(t) = input {
+        return extract.read(t.as_bytes(), f);
+    }
+    let mut reader = open_text(input, config)?;
+    extract.read(&mut reader, f)?;
+    report_decoding(input, &reader);
+    Ok(())
+}
+
+pub fn label(input: &Input) -> String {
+    match input {
+        Input::Stdin => "<stdin>".to_string(),
+        Input::Text(_) => "<text>".to_string(),
+        Input::File(path) => path.display().to_string(),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Documents {
+    Files,
+    Lines,
+    Paragraphs,
+}
+
+impl Documents {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "files" => Some(Documents::Files),
+            "lines" => Some(Documents::Lines),
+            "paragraphs" => Some(Documents::Paragraphs),
+            _ => None,
+        }
+    }
+}
+
+// Splits an input into one document per line, or per block of lines
+// separated by blank lines, calling `f` with the number of the first line
+// of each document.
+pub fn for_each_document<F: FnMut(usize, &str)>(
+    input: &Input,
+    documents: Documents,
+    config: &Config,
+    mut f: F,
+) -> io::Result<()> {
+    let mut splitter = DocumentSplitter {
+        documents,
+        line: String::new(),
+        line_number: 0,
+        block: String::new(),
+        block_start: 0,
+    };
+
+    match input {
+        Input::Text(t) => splitter.push(t, &mut f),
+        _ => {
+            let mut reader = open_text(input, config)?;
+            read_utf8_chunks(&mut reader, |text| splitter.push(text, &mut f))?;
+            report_decoding(input, &reader);
+        }
+    }
+    splitter.finish(&mut f);
+
+    Ok(())
+}
+
+struct DocumentSplitter {
+    documents: Documents,
+    line: String,
+    line_number: usize,
+    block: String,
+    block_start: usize,
+}
+
+impl DocumentSplitter {
+    fn push<F: FnMut(usize, &str)>(&mut self, text: &str, f: &mut F) {
+        let mut rest = text;
+        while let Some(end) = rest.find('\n') {
+            self.line.push_str(&rest[..end]);
+            self.end_line(f);
+            rest = &rest[end + 1..];
+        }
+        self.line.push_str(rest);
+    }
+
+    fn finish<F: FnMut(usize, &str)>(&mut self, f: &mut F) {
+        if !self.line.is_empty() {
+            self.end_line(f);
+        }
+        if !self.block.is_empty() {
+            f(self.block_start, &self.block);
+            self.block.clear();
+        }
+    }
+
+    fn end_line<F: FnMut(usize, &str)>(&mut self, f: &mut F) {
+        self.line_number += 1;
+        let line = mem::take(&mut self.line);
+
+        match self.documents {
+            Documents::Files | Documents::Lines => f(self.line_number, &line),
+            Documents::Paragraphs if line.trim().is_empty() => {
+                if !self.block.is_empty() {
+                    f(self.block_start, &self.block);
+                    self.block.clear();
+                }
+            }
+            Documents::Paragraphs => {
+                if self.block.is_empty() {
+                    self.block_start = self.line_number;
+                }
+                self.block.push_str(&line);
+                self.block.push('\n');
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        glob_match(&pattern, &text)
+    }
+
+    #[test]
+    fn stars_stay_within_a_directory_unless_doubled() {
+        assert!(glob("src/*.rs", "src/main.rs"));
+        assert!(!glob("src/*.rs", "src/bin/main.rs"));
+        assert!(glob("a?c", "abc"));
+        assert!(!glob("a?c", "a/c"));
+        assert!(glob("caf?.txt", "café.txt"));
+        assert!(glob("??.md", "日本.md"));
+        assert!(!glob("?.md", "日本.md"));
+
+        assert!(glob("src/**/*.rs", "src/main.rs"));
+        assert!(glob("src/**/*.rs", "src/bin/tools/main.rs"));
+        assert!(glob("**/fixtures", "fixtures"));
+        assert!(glob("**/fixtures", "tests/data/fixtures"));
+        assert!(glob("target/**", "target/debug/build"));
+        assert!(!glob("target/**", "src/target"));
+    }
+
+    #[test]
+    fn sets_match_one_character() {
+        assert!(glob("[abc].txt", "b.txt"));
+        assert!(!glob("[abc].txt", "d.txt"));
+        assert!(glob("log[0-9]", "log7"));
+        assert!(!glob("log[0-9]", "logx"));
+        assert!(glob("[!a-c]x", "dx"));
+        assert!(!glob("[!a-c]x", "bx"));
+        assert!(glob("[^0-9]", "z"));
+        assert!(!glob("[^0-9]", "5"));
+        // An unclosed bracket is literal.
+        assert!(glob("[ab", "[ab"));
+        assert!(glob("[à-é]x", "éx"));
+    }
+
+    #[test]
+    fn patterns_without_a_slash_match_the_file_name() {
+        assert!(path_matches("*.md", "docs/guide/README.md"));
+        assert!(path_matches("node_modules", "web/node_modules"));
+        assert!(!path_matches("docs/*.md", "README.md"));
+        assert!(path_matches("docs/*.md", "docs/README.md"));
+        assert!(!path_matches("docs/*.md", "web/docs/README.md"));
+    }
+}
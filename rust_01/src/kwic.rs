@@ -0,0 +1,147 @@
+use std::collections::HashSet;
+
+use crate::{Options, Unit, WordCounter};
+
+/// One occurrence of a word looked up in a [`Concordance`], with the text
+/// around it on its line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Occurrence<'t> {
+    /// The word as it is counted: normalized and, if stemming, stemmed.
+    pub key: String,
+    /// Position of the word in its line, in characters starting at 1.
+    pub column: usize,
+    pub left: &'t str,
+    pub word: &'t str,
+    pub right: &'t str,
+}
+
+/// Finds where chosen words occur, for a keyword-in-context view.
+///
+/// Lines are tokenized as written and each word is matched under the key
+/// it would be counted under, so with `ignore_case` looking up "rose" also
+/// finds "Rose", and with stemming "runs" finds "running".
+pub struct Concordance {
+    matcher: WordCounter,
+    keys: HashSet<String>,
+    context: usize,
+}
+
+impl Concordance {
+    /// Shows up to `context` characters on each side of an occurrence.
+    pub fn new(options: &Options, context: usize) -> Self {
+        Self {
+            matcher: WordCounter::new(options),
+            keys: HashSet::new(),
+            context,
+        }
+    }
+
+    /// Checks that `options` count single words, the only thing a
+    /// concordance can look up.
+    pub fn validate(options: &Options) -> Result<(), String> {
+        if options.ngram > 1 || options.unit != Unit::Word {
+            return Err("a concordance can only look up single words".to_string());
+        }
+        Ok(())
+    }
+
+    /// Looks up `word` as written, like a word of the text. Words that
+    /// would not be counted are ignored.
+    pub fn add_word(&mut self, word: &str) {
+        if let Some(key) = self.matcher.key(word) {
+            self.keys.insert(key.into_owned());
+        }
+    }
+
+    /// Looks up a key taken from the counts of a [`WordCounter`] built
+    /// with the same options.
+    pub fn add_key(&mut self, key: &str) {
+        self.keys.insert(key.to_string());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The occurrences in one line of text, in order.
+    pub fn find<'t>(&self, line: &'t str) -> Vec<Occurrence<'t>> {
+        let mut occurrences = Vec::new();
+
+        for (start, word) in self.matcher.tokenizer().word_indices(line) {
+            let Some(key) = self.matcher.key(word) else {
+                continue;
+            };
+            if !self.keys.contains(key.as_ref()) {
+                continue;
+            }
+
+            let end = start + word.len();
+            let word = &line[start..end];
+            let before = &line[..start];
+            let left_start = before
+                .char_indices()
+                .rev()
+                .take(self.context)
+                .last()
+                .map_or(start, |(index, _)| index);
+            let left = &before[left_start..];
+            let after = &line[end..];
+            let right = after
+                .char_indices()
+                .nth(self.context)
+                .map_or(after, |(index, _)| &after[..index]);
+
+            occurrences.push(Occurrence {
+                key: key.into_owned(),
+                column: before.chars().count() + 1,
+                left,
+                word,
+                right,
+            });
+        }
+
+        occurrences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Stemmer;
+
+    #[test]
+    fn finds_words_as_they_would_be_counted() {
+        let options = Options::new()
+            .ignore_case(true)
+            .stemmer(Some(Stemmer::English));
+        let mut concordance = Concordance::new(&options, 4);
+        concordance.add_word("Runs");
+
+        let found = concordance.find("I ran. She runs, we are running");
+        let words: Vec<(&str, &str, &str, usize)> = found
+            .iter()
+            .map(|o| (o.left, o.word, o.right, o.column))
+            .collect();
+        assert_eq!(
+            words,
+            [("She ", "runs", ", we", 12), ("are ", "running", "", 25)]
+        );
+        assert_eq!(found[0].key, "run");
+    }
+
+    #[test]
+    fn keys_are_matched_without_normalizing_again() {
+        let options = Options::new().ignore_case(true).min_length(3);
+        let mut concordance = Concordance::new(&options, 0);
+        concordance.add_key("the");
+        concordance.add_word("a");
+        assert!(!concordance.is_empty());
+
+        let found = concordance.find("A cat, THE hat");
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            (found[0].left, found[0].word, found[0].right),
+            ("", "THE", "")
+        );
+    }
+}
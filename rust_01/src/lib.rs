@@ -0,0 +1,40 @@
+//! Word frequency counting.
+//!
+//! A [`WordCounter`] is fed text incrementally, from `&str` chunks or any
+//! [`Read`](std::io::Read), and keeps only the counts resident. Counters
+//! built with the same [`Options`] can be merged, which is how the
+//! `wordfreq` binary counts files in parallel, and [`WordCounter::ranked`]
+//! yields the results by count, then word.
+
+mod code;
+mod collocation;
+mod compare;
+mod counter;
+mod decode;
+mod extract;
+mod kwic;
+mod normalize;
+mod options;
+mod stats;
+mod stem;
+pub mod stopwords;
+pub mod store;
+mod tfidf;
+mod tokenizer;
+mod topk;
+
+pub use code::{lex, split_identifier, Category, CodeCounter, Language};
+pub use collocation::{Association, Collocation, Collocations};
+pub use compare::{compare, CompareBy, WordChange};
+pub use counter::{
+    read_chunks, read_utf8_chunks, Ranked, RankedWord, Segmenter, Unit, WordCounter,
+};
+pub use decode::{Encoding, TextReader};
+pub use extract::{html_text, markdown_text, Column, Extract, JsonPath};
+pub use kwic::{Concordance, Occurrence};
+pub use normalize::Form;
+pub use options::Options;
+pub use stats::{stats, Stats, ZipfFit};
+pub use stem::Stemmer;
+pub use tfidf::{TermScore, TfIdf};
+pub use tokenizer::Tokenizer;
@@ -1,119 +1,996 @@
-use std::collections::HashMap;
+mod chart;
+mod cli;
+mod follow;
+mod input;
+mod output;
+
+use std::collections::BTreeMap;
 use std::env;
-use std::io::{self, Read};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, IsTerminal, Read};
 use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use regex::{Regex, RegexSet};
+use wordfreq::{
+    compare, read_utf8_chunks, stopwords, store, Association, CodeCounter, Collocations, Column,
+    CompareBy, Concordance, Encoding, Extract, Form, JsonPath, Language, Options, Segmenter,
+    Stemmer, TfIdf, Tokenizer, Unit, WordCounter,
+};
+
+use chart::SvgStyle;
+use cli::Arg;
+use follow::Window;
+use input::{Documents, Input};
+use output::{Format, Printer};
 
 struct Config {
     top: usize,
-    min_length: usize,
-    ignore_case: bool,
-    text: Option<String>,
+    options: Options,
+    stop_word_lists: Vec<String>,
+    surface_forms: bool,
+    inputs: Vec<String>,
+    literal_text: bool,
+    extract: Option<Extract>,
+    encoding: Option<Encoding>,
+    lossy: bool,
+    include_files: Vec<String>,
+    exclude_files: Vec<String>,
+    jobs: usize,
+    format: Format,
+    header: bool,
+    chart: bool,
+    color: bool,
+    log_scale: bool,
+    percent: bool,
+    svg: Option<String>,
+    svg_style: SvgStyle,
+    compare_by: Option<CompareBy>,
+    tfidf: bool,
+    code: bool,
+    code_language: Option<Language>,
+    split_identifiers: bool,
+    documents: Documents,
+    kwic: Vec<String>,
+    kwic_top: Option<usize>,
+    context: usize,
+    collocations: Option<Association>,
+    span: usize,
+    min_pair_count: usize,
+    min_word_count: usize,
+    load: Vec<String>,
+    save: Option<String>,
+    stats: bool,
+    follow: bool,
+    interval: Duration,
+    window: Window,
     top_specified: bool,
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    // Die quietly when the reader of our output goes away, like other
+    // filters, instead of panicking in println!.
+    #[cfg(unix)]
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
 
     let mut config = Config {
         top: 10,
-        min_length: 1,
-        ignore_case: false,
-        text: None,
+        options: Options::new(),
+        stop_word_lists: Vec::new(),
+        surface_forms: false,
+        inputs: Vec::new(),
+        literal_text: false,
+        extract: None,
+        encoding: None,
+        lossy: false,
+        include_files: Vec::new(),
+        exclude_files: Vec::new(),
+        jobs: 1,
+        format: Format::Text,
+        header: true,
+        chart: false,
+        color: false,
+        log_scale: false,
+        percent: false,
+        svg: None,
+        svg_style: SvgStyle::Bars,
+        compare_by: None,
+        tfidf: false,
+        code: false,
+        code_language: None,
+        split_identifiers: false,
+        documents: Documents::Files,
+        kwic: Vec::new(),
+        kwic_top: None,
+        context: 30,
+        collocations: None,
+        span: 1,
+        min_pair_count: 3,
+        min_word_count: 1,
+        load: Vec::new(),
+        save: None,
+        stats: false,
+        follow: false,
+        interval: Duration::from_secs(2),
+        window: Window::All,
         top_specified: false,
     };
 
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "-h" | "--help" => {
-                print_help();
+    let mut compare = false;
+    let mut compare_by = CompareBy::Change;
+    let mut collocations = false;
+    let mut collocations_by = Association::LogLikelihood;
+    let mut approximate = None;
+    let mut color = None;
+    let mut include = Vec::new();
+    let mut exclude = Vec::new();
+    let mut json_paths = Vec::new();
+    let mut csv_columns = Vec::new();
+    let mut csv_delimiter = None;
+    let mut csv_header = true;
+    let mut markup = Vec::new();
+    for arg in cli::args().unwrap_or_else(|e| cli::fail(&e)) {
+        let (flag, value) = match arg {
+            Arg::Flag(flag, value) => (flag, value),
+            Arg::Input(input) => {
+                config.inputs.push(input);
+                continue;
+            }
+        };
+        match flag.long {
+            "help" => {
+                cli::print_help();
                 return;
             }
-            "--top" => {
-                if i + 1 < args.len() {
-                    if let Ok(n) = args[i + 1].parse() {
-                        config.top = n;
-                        config.top_specified = true;
-                        i += 1;
-                    }
-                }
+            "version" => {
+                println!("wordfreq {}", env!("CARGO_PKG_VERSION"));
+                return;
             }
-            "--min-length" => {
-                if i + 1 < args.len() {
-                    if let Ok(n) = args[i + 1].parse() {
-                        config.min_length = n;
-                        i += 1;
-                    }
-                }
+            "completions" => {
+                let script =
+                    cli::completions(&value).unwrap_or_else(|| cli::invalid(&flag, &value));
+                print!("{}", script);
+                return;
+            }
+            "man" => {
+                print!("{}", cli::man_page());
+                return;
             }
-            "--ignore-case" => {
-                config.ignore_case = true;
+            "no-config" => {}
+            "top" => {
+                config.top = cli::number(&flag, &value, 1);
+                config.top_specified = true;
             }
-            arg => {
-                if arg.starts_with('-') {
-                    eprintln!("error: Unknown option {}", arg);
-                    process::exit(2);
+            "min-length" => {
+                config.options = config.options.min_length(cli::number(&flag, &value, 0));
+            }
+            "ignore-case" => {
+                config.options = config.options.ignore_case(true);
+            }
+            "case-fold" => {
+                config.options = config.options.case_fold(true);
+            }
+            "normalize" => {
+                let form = Form::parse(&value).unwrap_or_else(|| cli::invalid(&flag, &value));
+                config.options = config.options.normalization(Some(form));
+            }
+            "strip-diacritics" => {
+                config.options = config.options.strip_diacritics(true);
+            }
+            "tokenizer" => {
+                let tokenizer =
+                    Tokenizer::parse(&value).unwrap_or_else(|| cli::invalid(&flag, &value));
+                config.options = config.options.tokenizer(tokenizer);
+            }
+            "token-pattern" => match Regex::new(&value) {
+                Ok(pattern) => {
+                    config.options = config.options.tokenizer(Tokenizer::Pattern(pattern))
                 }
-                config.text = Some(arg.to_string());
+                Err(e) => cli::fail(&format!("Invalid {}: {}", flag.name(), e)),
+            },
+            "include" => include.push(value),
+            "exclude" => exclude.push(value),
+            "stopwords" => config.stop_word_lists.push(value),
+            "stem" => {
+                let stemmer = Stemmer::parse(&value).unwrap_or_else(|| cli::invalid(&flag, &value));
+                config.options = config.options.stemmer(Some(stemmer));
+            }
+            "surface-forms" => {
+                config.surface_forms = true;
+            }
+            "ngram" => {
+                config.options = config.options.ngram(cli::number(&flag, &value, 1));
+            }
+            "sentence-boundaries" => {
+                config.options = config.options.sentence_boundaries(true);
+            }
+            "unit" => {
+                let unit = Unit::parse(&value).unwrap_or_else(|| cli::invalid(&flag, &value));
+                config.options = config.options.unit(unit);
+            }
+            "encoding" => {
+                config.encoding = match value.as_str() {
+                    "auto" => None,
+                    _ => Some(Encoding::parse(&value).unwrap_or_else(|| {
+                        cli::fail(&format!("Unknown encoding {} for {}", value, flag.name()))
+                    })),
+                };
+            }
+            "lossy" => {
+                config.lossy = true;
+            }
+            "text" => {
+                config.literal_text = true;
+            }
+            "json-path" => json_paths.push(JsonPath::parse(&value)),
+            "csv-column" => {
+                csv_columns
+                    .push(Column::parse(&value).unwrap_or_else(|| cli::invalid(&flag, &value)));
+            }
+            "csv-delimiter" => {
+                csv_delimiter = match value.as_str() {
+                    "tab" | "\\t" => Some(b'\t'),
+                    _ if value.len() == 1 && value.is_ascii() => Some(value.as_bytes()[0]),
+                    _ => cli::invalid(&flag, &value),
+                };
+            }
+            "csv-no-header" => {
+                csv_header = false;
+            }
+            "html" => markup.push(Extract::Html),
+            "markdown" => markup.push(Extract::Markdown),
+            "include-files" => config.include_files.push(value),
+            "exclude-files" => config.exclude_files.push(value),
+            "jobs" => {
+                config.jobs = cli::number(&flag, &value, 1);
+            }
+            "format" => {
+                config.format =
+                    Format::parse(&value).unwrap_or_else(|| cli::invalid(&flag, &value));
+            }
+            "compare" => {
+                compare = true;
+            }
+            "compare-by" => {
+                compare_by =
+                    CompareBy::parse(&value).unwrap_or_else(|| cli::invalid(&flag, &value));
+            }
+            "code" => {
+                config.code = true;
+            }
+            "code-language" => {
+                let language =
+                    Language::parse(&value).unwrap_or_else(|| cli::invalid(&flag, &value));
+                config.code_language = Some(language);
+            }
+            "split-identifiers" => {
+                config.split_identifiers = true;
+            }
+            "tfidf" => {
+                config.tfidf = true;
+            }
+            "documents" => {
+                config.documents =
+                    Documents::parse(&value).unwrap_or_else(|| cli::invalid(&flag, &value));
+            }
+            "kwic" => config.kwic.push(value),
+            "kwic-top" => {
+                config.kwic_top = Some(cli::number(&flag, &value, 1));
+            }
+            "context" => {
+                config.context = cli::number(&flag, &value, 0);
+            }
+            "collocations" => {
+                collocations = true;
+            }
+            "collocations-by" => {
+                collocations_by =
+                    Association::parse(&value).unwrap_or_else(|| cli::invalid(&flag, &value));
+            }
+            "span" => {
+                config.span = cli::number(&flag, &value, 1);
+            }
+            "min-pair-count" => {
+                config.min_pair_count = cli::number(&flag, &value, 1);
+            }
+            "min-word-count" => {
+                config.min_word_count = cli::number(&flag, &value, 1);
+            }
+            "load" => config.load.push(value),
+            "save" => config.save = Some(value),
+            "approximate" => {
+                approximate = Some(cli::number(&flag, &value, 1));
+            }
+            "stats" => {
+                config.stats = true;
+            }
+            "follow" => {
+                config.follow = true;
+            }
+            "interval" => match value.parse::<f64>() {
+                Ok(s) if s > 0.0 && s.is_finite() => config.interval = Duration::from_secs_f64(s),
+                _ => cli::invalid(&flag, &value),
+            },
+            "window-lines" => {
+                config.window = Window::Lines(cli::number(&flag, &value, 1));
+            }
+            "window-seconds" => {
+                config.window = Window::Seconds(cli::number(&flag, &value, 1));
+            }
+            "chart" => {
+                config.chart = true;
+            }
+            "color" => {
+                color = match value.as_str() {
+                    "auto" => None,
+                    "always" => Some(true),
+                    "never" => Some(false),
+                    _ => cli::invalid(&flag, &value),
+                };
+            }
+            "log-scale" => {
+                config.log_scale = true;
+            }
+            "percent" => {
+                config.percent = true;
+            }
+            "svg" => config.svg = Some(value),
+            "svg-style" => {
+                config.svg_style =
+                    SvgStyle::parse(&value).unwrap_or_else(|| cli::invalid(&flag, &value));
+            }
+            "no-header" => {
+                config.header = false;
+            }
+            _ => unreachable!("--{} is not handled", flag.long),
+        }
+    }
+    // The measures only tune their mode, so that they can be set as
+    // defaults without turning it on.
+    config.compare_by = compare.then_some(compare_by);
+    config.collocations = collocations.then_some(collocations_by);
+
+    let saved_state = !config.load.is_empty() || config.save.is_some();
+    if saved_state && (config.compare_by.is_some() || config.tfidf) {
+        eprintln!("error: --load and --save cannot be combined with --compare or --tfidf");
+        process::exit(2);
+    }
+
+    if config.follow && (saved_state || config.compare_by.is_some() || config.tfidf) {
+        eprintln!("error: --follow cannot be combined with --load, --save, --compare or --tfidf");
+        process::exit(2);
+    }
+    if config.follow && (config.encoding.is_some() || config.lossy) {
+        eprintln!(
+            "error: --follow reads UTF-8 only and cannot be combined with --encoding or --lossy"
+        );
+        process::exit(2);
+    }
+    if config.window != Window::All && !config.follow {
+        eprintln!("error: --window-lines and --window-seconds need --follow");
+        process::exit(2);
+    }
+
+    if config.code && (saved_state || config.compare_by.is_some() || config.tfidf || config.follow)
+    {
+        eprintln!(
+            "error: --code cannot be combined with --load, --save, --compare, --tfidf or --follow"
+        );
+        process::exit(2);
+    }
+    if config.code {
+        if let Err(e) = CodeCounter::validate(&config.options) {
+            eprintln!("error: {}", e);
+            process::exit(2);
+        }
+    }
+
+    let kwic = !config.kwic.is_empty() || config.kwic_top.is_some();
+    if kwic
+        && (saved_state
+            || config.compare_by.is_some()
+            || config.tfidf
+            || config.code
+            || config.follow
+            || config.stats
+            || config.chart
+            || config.svg.is_some())
+    {
+        eprintln!("error: --kwic and --kwic-top only list occurrences and cannot be combined with other modes");
+        process::exit(2);
+    }
+    if kwic {
+        if let Err(e) = Concordance::validate(&config.options) {
+            eprintln!("error: {}", e);
+            process::exit(2);
+        }
+    }
+
+    if config.collocations.is_some()
+        && (saved_state
+            || config.compare_by.is_some()
+            || config.tfidf
+            || config.code
+            || kwic
+            || config.follow
+            || config.stats
+            || config.surface_forms
+            || config.chart
+            || config.svg.is_some()
+            || approximate.is_some())
+    {
+        eprintln!("error: --collocations ranks word pairs and cannot be combined with other modes");
+        process::exit(2);
+    }
+    if config.collocations.is_some() {
+        if let Err(e) = Collocations::validate(&config.options) {
+            eprintln!("error: {}", e);
+            process::exit(2);
+        }
+    }
+
+    let modes = [!json_paths.is_empty(), !csv_columns.is_empty()]
+        .into_iter()
+        .filter(|&given| given)
+        .count()
+        + markup.len();
+    if modes > 1 {
+        eprintln!(
+            "error: only one of --json-path, --csv-column, --html and --markdown can be given"
+        );
+        process::exit(2);
+    }
+    if (csv_delimiter.is_some() || !csv_header) && csv_columns.is_empty() {
+        eprintln!("error: --csv-delimiter and --csv-no-header need --csv-column");
+        process::exit(2);
+    }
+    if !csv_header && csv_columns.iter().any(|c| matches!(c, Column::Name(_))) {
+        eprintln!("error: --csv-no-header needs --csv-column to be given as a number");
+        process::exit(2);
+    }
+    config.extract = if !json_paths.is_empty() {
+        Some(Extract::Json(json_paths))
+    } else if !csv_columns.is_empty() {
+        Some(Extract::Csv {
+            columns: csv_columns,
+            delimiter: csv_delimiter.unwrap_or(b','),
+            header: csv_header,
+        })
+    } else {
+        markup.pop()
+    };
+    if config.extract.is_some()
+        && (config.code
+            || kwic
+            || config.follow
+            || (config.tfidf && config.documents != Documents::Files))
+    {
+        eprintln!(
+            "error: --json-path, --csv-column, --html and --markdown cannot be combined with --code, --kwic, --follow or --documents lines/paragraphs"
+        );
+        process::exit(2);
+    }
+
+    if config.surface_forms {
+        if saved_state || config.compare_by.is_some() || config.tfidf || config.code {
+            eprintln!(
+                "error: --surface-forms cannot be combined with --load, --save, --compare, --tfidf or --code"
+            );
+            process::exit(2);
+        }
+        config.options = config.options.surface_forms(true);
+    }
+
+    if config.chart && config.format != Format::Text {
+        eprintln!("error: --chart is only drawn with --format text");
+        process::exit(2);
+    }
+    if (config.chart || config.svg.is_some()) && (config.compare_by.is_some() || config.tfidf) {
+        eprintln!("error: --chart and --svg cannot be combined with --compare or --tfidf");
+        process::exit(2);
+    }
+    if config.svg.is_some() && (config.code || config.follow) {
+        eprintln!("error: --svg cannot be combined with --code or --follow");
+        process::exit(2);
+    }
+    if (config.log_scale || config.percent) && !config.chart && config.svg.is_none() {
+        eprintln!("error: --log-scale and --percent need --chart or --svg");
+        process::exit(2);
+    }
+    // Colours only go to a terminal unless asked for, and NO_COLOR turns
+    // them off by default.
+    config.color = color.unwrap_or_else(|| {
+        io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+    });
+
+    if config.stats && (config.compare_by.is_some() || config.tfidf || approximate.is_some()) {
+        eprintln!("error: --stats cannot be combined with --compare, --tfidf or --approximate");
+        process::exit(2);
+    }
+
+    if let Some(capacity) = approximate {
+        if config.window != Window::All {
+            eprintln!("error: --approximate cannot be combined with a --follow window");
+            process::exit(2);
+        }
+        if saved_state || config.compare_by.is_some() || config.tfidf {
+            eprintln!(
+                "error: --approximate cannot be combined with --load, --save, --compare or --tfidf"
+            );
+            process::exit(2);
+        }
+        if capacity < config.top {
+            eprintln!("error: --approximate must track at least as many words as --top");
+            process::exit(2);
+        }
+        config.options = config.options.approximate(Some(capacity));
+    }
+
+    for (flag, patterns) in [("--include", &include), ("--exclude", &exclude)] {
+        if patterns.is_empty() {
+            continue;
+        }
+        let set = match RegexSet::new(patterns) {
+            Ok(set) => set,
+            Err(e) => {
+                eprintln!("error: Invalid {}: {}", flag, e);
+                process::exit(2);
             }
+        };
+        config.options = if flag == "--include" {
+            config.options.include(Some(set))
+        } else {
+            config.options.exclude(Some(set))
+        };
+    }
+
+    if let Err(e) = config.options.validate() {
+        eprintln!("error: {}", e);
+        process::exit(2);
+    }
+
+    match stopwords::load(&config.stop_word_lists) {
+        Ok(stop_words) => config.options = config.options.stop_words(stop_words),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(2);
         }
-        i += 1;
     }
 
-    let content = match config.text {
-        Some(t) => t,
-        None => {
-            let mut buffer = String::new();
-            if io::stdin().read_to_string(&mut buffer).is_err() {
+    if let Some(by) = config.compare_by {
+        run_compare(&config, by);
+        return;
+    }
+
+    // Loaded counts stand in for stdin when no other input is given.
+    let inputs = if !config.load.is_empty() && config.inputs.is_empty() {
+        Vec::new()
+    } else {
+        match input::collect_inputs(&config.inputs, &config) {
+            Ok(inputs) => inputs,
+            Err(e) => {
+                eprintln!("error: {}", e);
                 process::exit(1);
             }
-            buffer
         }
     };
 
-    let text_to_process = if config.ignore_case {
-        content.to_lowercase()
+    if saved_state {
+        run_saved_state(&config, &inputs);
+        return;
+    }
+
+    if config.code {
+        run_code(&config, &inputs);
+        return;
+    }
+
+    if !config.kwic.is_empty() || config.kwic_top.is_some() {
+        run_kwic(&config, &inputs);
+        return;
+    }
+
+    if let Some(by) = config.collocations {
+        run_collocations(&config, &inputs, by);
+        return;
+    }
+
+    if config.follow {
+        if inputs.len() != 1 {
+            eprintln!("error: --follow needs exactly one file or stdin");
+            process::exit(2);
+        }
+        follow::run(&config, &inputs[0]);
+        return;
+    }
+
+    if config.tfidf {
+        run_tfidf(&config, &inputs);
+        return;
+    }
+
+    if inputs.len() == 1 {
+        let counter = if config.jobs > 1 {
+            count_input_parallel(&inputs[0], &config)
+        } else {
+            count_input(&inputs[0], &config)
+        };
+        Printer::new(&config, false).single(&counter);
+        write_svg(&config, &counter);
+        return;
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let next_input = AtomicUsize::new(0);
+    let (config, inputs, next_input) = (&config, &inputs, &next_input);
+
+    thread::scope(|scope| {
+        for _ in 0..config.jobs.min(inputs.len()) {
+            let sender = sender.clone();
+            scope.spawn(move || loop {
+                let index = next_input.fetch_add(1, Ordering::Relaxed);
+                if index >= inputs.len() {
+                    break;
+                }
+                let counter = count_input(&inputs[index], config);
+                if sender.send((index, counter)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        // Workers finish in any order; reports are still printed in input order.
+        let mut printer = Printer::new(config, true);
+        let mut finished = BTreeMap::new();
+        let mut next_report = 0;
+        let mut total = WordCounter::new(&config.options);
+        for (index, counter) in receiver {
+            finished.insert(index, counter);
+            while let Some(counter) = finished.remove(&next_report) {
+                printer.report(&input::label(&inputs[next_report]), &counter);
+                total.merge(counter);
+                next_report += 1;
+            }
+        }
+
+        printer.total(&total);
+        write_svg(config, &total);
+    });
+}
+
+// Each side of a comparison is one argument: a file, a directory whose
+// files are merged, a literal text with --text, or - for stdin.
+fn run_compare(config: &Config, by: CompareBy) {
+    if config.inputs.len() != 2 {
+        eprintln!("error: --compare needs exactly two inputs");
+        process::exit(2);
+    }
+
+    let sides: Vec<WordCounter> = config
+        .inputs
+        .iter()
+        .map(|arg| {
+            let inputs = match input::collect_inputs(std::slice::from_ref(arg), config) {
+                Ok(inputs) => inputs,
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    process::exit(1);
+                }
+            };
+            count_merged(&inputs, config)
+        })
+        .collect();
+
+    let labels = if config.literal_text {
+        ["<text 1>", "<text 2>"]
     } else {
-        content
+        [config.inputs[0].as_str(), config.inputs[1].as_str()]
     };
+    let changes = compare(&sides[0], &sides[1], by);
+    output::print_comparison(config, labels, [&sides[0], &sides[1]], by, &changes);
+}
 
-    let mut word_counts: HashMap<String, usize> = HashMap::new();
-    let words = text_to_process.split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '"');
+fn run_tfidf(config: &Config, inputs: &[Input]) {
+    let mut documents: Vec<(String, WordCounter)> = Vec::new();
 
-    for word in words {
-        if !word.is_empty() && word.len() >= config.min_length {
-            *word_counts.entry(word.to_string()).or_insert(0) += 1;
+    for input in inputs {
+        let label = input::label(input);
+        if config.documents == Documents::Files {
+            documents.push((label, count_input(input, config)));
+            continue;
         }
+
+        // Documents without a counted token are skipped but keep their
+        // line number in the label.
+        let result = input::for_each_document(input, config.documents, config, |line, text| {
+            let mut counter = WordCounter::new(&config.options);
+            counter.feed(text);
+            counter.finish();
+            if counter.total() > 0 {
+                documents.push((format!("{}:{}", label, line), counter));
+            }
+        });
+        report_read_error(input, result);
     }
 
-    let mut sorted_words: Vec<(&String, &usize)> = word_counts.iter().collect();
+    let mut tfidf = TfIdf::new();
+    for (_, counter) in &documents {
+        tfidf.add_document(counter);
+    }
+    output::print_tfidf(config, &tfidf, &documents);
+}
 
-    sorted_words.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+// Counts every source file into per-category totals. Without
+// --code-language the language comes from the file extension, and files
+// of unknown languages are skipped.
+fn run_code(config: &Config, inputs: &[Input]) {
+    let mut total = CodeCounter::new(&config.options, config.split_identifiers);
+    let mut files = 0;
+    let mut skipped = 0;
 
-    if config.top_specified {
-        println!("Top {} words:", config.top);
-    } else {
-        println!("Word frequency:");
+    for input in inputs {
+        let language = match (config.code_language, input) {
+            (Some(language), _) => language,
+            (None, Input::File(path)) => match Language::from_path(path) {
+                Some(language) => language,
+                None => {
+                    skipped += 1;
+                    continue;
+                }
+            },
+            (None, _) => {
+                eprintln!("error: --code-language is needed to count code from stdin or --text");
+                process::exit(2);
+            }
+        };
+
+        let mut source = String::new();
+        let result = input::open_text(input, config).and_then(|mut reader| {
+            reader.read_to_string(&mut source)?;
+            input::report_decoding(input, &reader);
+            Ok(())
+        });
+        report_read_error(input, result);
+
+        total.feed_source(&source, language);
+        files += 1;
     }
+    total.finish();
 
-    for (word, count) in sorted_words.iter().take(config.top) {
-        println!("{}: {}", word, format_number(**count));
+    if skipped > 0 {
+        eprintln!(
+            "warning: skipped {} files of unknown language (use --code-language)",
+            skipped
+        );
     }
+    output::print_code(config, &total, files);
 }
 
-fn print_help() {
-    println!("Usage: wordfreq [OPTIONS]");
-    println!();
-    println!("Count word frequency in text");
-    println!();
-    println!("Arguments:");
-    println!("Text to analyze (or use stdin)");
-    println!();
-    println!("Options:");
-    println!("--top Show top N words [default: 10]");
-    println!("--min-length Ignore words shorter than N [default: 1]");
-    println!("--ignore-case Case insensitive counting");
-    println!("-h, --help");
+// Lists every occurrence of the --kwic words, and of the --kwic-top most
+// frequent words, which takes a first pass counting the inputs.
+fn run_kwic(config: &Config, inputs: &[Input]) {
+    let mut concordance = Concordance::new(&config.options, config.context);
+    for word in &config.kwic {
+        concordance.add_word(word);
+    }
+    if let Some(n) = config.kwic_top {
+        if inputs.iter().any(|input| matches!(input, Input::Stdin)) {
+            eprintln!("error: --kwic-top reads its inputs twice and cannot read stdin");
+            process::exit(2);
+        }
+        for entry in count_merged(inputs, config).ranked().take(n) {
+            concordance.add_key(entry.word);
+        }
+    } else if concordance.is_empty() {
+        eprintln!("error: none of the --kwic words would be counted with these options");
+        process::exit(2);
+    }
+
+    let mut printer = output::KwicPrinter::new(config, inputs.len() > 1);
+    for input in inputs {
+        let label = input::label(input);
+        let result = input::for_each_document(input, Documents::Lines, config, |line, text| {
+            let text = text.strip_suffix('\r').unwrap_or(text);
+            for occurrence in concordance.find(text) {
+                printer.occurrence(&label, line, &occurrence);
+            }
+        });
+        report_read_error(input, result);
+    }
+    printer.finish();
+}
+
+// Pairs are counted per input and merged, so no pair spans two files.
+fn run_collocations(config: &Config, inputs: &[Input], by: Association) {
+    let mut total = Collocations::new(&config.options, config.span);
+    for input in inputs {
+        let mut collocations = Collocations::new(&config.options, config.span);
+        let result = match (input, &config.extract) {
+            (_, Some(extract)) => input::extract_text(input, extract, config, |text| {
+                collocations.feed(text);
+                collocations.feed("\n");
+            }),
+            (Input::Text(t), None) => {
+                collocations.feed(t);
+                Ok(())
+            }
+            _ => input::open_text(input, config).and_then(|mut reader| {
+                read_utf8_chunks(&mut reader, |text| collocations.feed(text))?;
+                input::report_decoding(input, &reader);
+                Ok(())
+            }),
+        };
+        report_read_error(input, result);
+        collocations.finish();
+        total.merge(collocations);
+    }
+    output::print_collocations(config, &total, by);
+}
+
+// Merges the loaded counts with the counts of `inputs` into one report,
+// which is the same as the total of a run over everything counted so far.
+fn run_saved_state(config: &Config, inputs: &[Input]) {
+    let mut total = WordCounter::new(&config.options);
+    for path in &config.load {
+        let loaded =
+            File::open(path).and_then(|file| store::load(BufReader::new(file), &config.options));
+        match loaded {
+            Ok(counter) => total.merge(counter),
+            Err(e) => {
+                eprintln!("error: {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+    }
+    if !inputs.is_empty() {
+        total.merge(count_merged(inputs, config));
+    }
+
+    if let Some(path) = &config.save {
+        // Written next to the target and renamed over it, so an interrupted
+        // run never leaves a truncated state behind.
+        let temporary = format!("{}.tmp", path);
+        let result = File::create(&temporary)
+            .and_then(|file| store::save(&total, &config.options, BufWriter::new(file)))
+            .and_then(|()| fs::rename(&temporary, path));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&temporary);
+            eprintln!("error: {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+
+    Printer::new(config, false).single(&total);
+    write_svg(config, &total);
+}
+
+fn write_svg(config: &Config, counter: &WordCounter) {
+    if let Some(path) = &config.svg {
+        if let Err(e) = fs::write(path, chart::svg(counter, config)) {
+            eprintln!("error: {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn count_merged(inputs: &[Input], config: &Config) -> WordCounter {
+    if inputs.len() == 1 && config.jobs > 1 {
+        return count_input_parallel(&inputs[0], config);
+    }
+
+    let mut total = WordCounter::new(&config.options);
+    for input in inputs {
+        total.merge(count_input(input, config));
+    }
+    total
+}
+
+fn count_input(input: &Input, config: &Config) -> WordCounter {
+    let mut counter = WordCounter::new(&config.options);
+
+    let result = match (input, &config.extract) {
+        // Each extracted piece ends like a line, so words of neighbouring
+        // fields are not joined.
+        (_, Some(extract)) => input::extract_text(input, extract, config, |text| {
+            counter.feed(text);
+            counter.feed("\n");
+        }),
+        (Input::Text(t), None) => {
+            counter.feed(t);
+            Ok(())
+        }
+        // Bytes are counted as they are, only decompressed.
+        _ if counter.unit() == Unit::Byte => {
+            input::open(input).and_then(|reader| counter.feed_reader(reader))
+        }
+        _ => input::open_text(input, config).and_then(|mut reader| {
+            counter.feed_reader(&mut reader)?;
+            input::report_decoding(input, &reader);
+            Ok(())
+        }),
+    };
+    report_read_error(input, result);
+    counter.finish();
+
+    counter
+}
+
+// A single large input is split at safe boundaries by this thread and the
+// segments are tokenized by `config.jobs` workers into their own counters.
+fn count_input_parallel(input: &Input, config: &Config) -> WordCounter {
+    // Workers see segments out of order, so a sentence cut between two of
+    // them would be counted twice.
+    if !config.options.splittable() || config.stats || config.extract.is_some() {
+        return count_input(input, config);
+    }
+
+    if let Input::Text(_) = input {
+        return count_input(input, config);
+    }
+    let mut reader = match input::open_text(input, config) {
+        Ok(reader) => reader,
+        Err(e) => {
+            eprintln!("error: {}: {}", input::label(input), e);
+            process::exit(1);
+        }
+    };
+
+    let (sender, receiver) = mpsc::sync_channel::<String>(config.jobs * 2);
+    let receiver = Mutex::new(receiver);
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..config.jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut counter = WordCounter::new(&config.options);
+                    loop {
+                        let segment = receiver.lock().unwrap().recv();
+                        match segment {
+                            Ok(segment) => counter.count_segment(&segment),
+                            Err(_) => break,
+                        }
+                    }
+                    counter
+                })
+            })
+            .collect();
+
+        let mut segmenter = Segmenter::new(&config.options);
+        let result = read_utf8_chunks(&mut reader, |text| {
+            if let Some(ready) = segmenter.push(text) {
+                let _ = sender.send(ready);
+            }
+        });
+        input::report_decoding(input, &reader);
+        let _ = sender.send(segmenter.finish());
+        drop(sender);
+        report_read_error(input, result);
+
+        let mut total = WordCounter::new(&config.options);
+        for worker in workers {
+            total.merge(worker.join().unwrap());
+        }
+        total
+    })
+}
+
+fn report_read_error(input: &Input, result: io::Result<()>) {
+    if let Err(e) = result {
+        // Undecodable text is the usual cause of invalid data.
+        let hint = if e.kind() == io::ErrorKind::InvalidData {
+            " (choose the input encoding with --encoding, or use --lossy)"
+        } else {
+            ""
+        };
+        eprintln!("error: {}: {}{}", input::label(input), e, hint);
+        process::exit(1);
+    }
 }
 
 fn format_number(n: usize) -> String {
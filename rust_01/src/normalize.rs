@@ -0,0 +1,137 @@
+use std::borrow::Cow;
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::Options;
+
+/// Unicode normalization form applied before counting.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Form {
+    Nfc,
+    Nfkc,
+}
+
+impl Form {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "nfc" => Some(Form::Nfc),
+            "nfkc" => Some(Form::Nfkc),
+            _ => None,
+        }
+    }
+
+    fn apply(self, text: &str) -> String {
+        match self {
+            Form::Nfc => text.nfc().collect(),
+            Form::Nfkc => text.nfkc().collect(),
+        }
+    }
+}
+
+// Turns raw text into the form words are counted in. Every step works on
+// single characters or on a base character plus its combining marks, so
+// cutting the input at whitespace never changes the result.
+pub(crate) struct Normalizer {
+    lowercase: bool,
+    case_fold: bool,
+    form: Option<Form>,
+    strip_diacritics: bool,
+}
+
+impl Normalizer {
+    pub fn new(options: &Options) -> Self {
+        Self {
+            lowercase: options.ignore_case,
+            case_fold: options.case_fold,
+            form: options.normalization,
+            strip_diacritics: options.strip_diacritics,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.lowercase || self.case_fold || self.form.is_some() || self.strip_diacritics
+    }
+
+    pub fn apply<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        let mut text = Cow::Borrowed(text);
+
+        if let Some(form) = self.form {
+            text = Cow::Owned(form.apply(&text));
+        }
+
+        if self.case_fold {
+            text = Cow::Owned(caseless::default_case_fold_str(&text));
+            // Folding can leave text unnormalized (e.g. U+0345 folds to a
+            // plain iota), so the requested form is applied again.
+            if let Some(form) = self.form {
+                text = Cow::Owned(form.apply(&text));
+            }
+        } else if self.lowercase {
+            text = Cow::Owned(text.to_lowercase());
+        }
+
+        if self.strip_diacritics {
+            text = Cow::Owned(
+                text.nfd()
+                    .filter(|c| !is_combining_mark(*c))
+                    .nfc()
+                    .collect(),
+            );
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WordCounter;
+
+    fn normalized(options: &Options, text: &str) -> String {
+        Normalizer::new(options).apply(text).into_owned()
+    }
+
+    #[test]
+    fn case_folding_goes_beyond_lowercase() {
+        let lowercase = Options::new().ignore_case(true);
+        assert_eq!(normalized(&lowercase, "Straße STRASSE"), "straße strasse");
+
+        let folded = Options::new().case_fold(true);
+        assert_eq!(normalized(&folded, "Straße STRASSE"), "strasse strasse");
+        assert_eq!(normalized(&folded, "ΣΊΣΥΦΟΣ σίσυφος"), "σίσυφοσ σίσυφοσ");
+
+        let mut counter = WordCounter::new(&folded);
+        counter.feed("Straße strasse STRASSE");
+        counter.finish();
+        assert_eq!(counter.counts().get("strasse"), Some(&3));
+    }
+
+    #[test]
+    fn composed_and_decomposed_text_count_alike() {
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+        assert_ne!(normalized(&Options::new(), decomposed), composed);
+
+        let nfc = Options::new().normalization(Some(Form::Nfc));
+        assert_eq!(normalized(&nfc, decomposed), composed);
+        assert_eq!(normalized(&nfc, composed), composed);
+        assert_eq!(normalized(&nfc, "\u{fb01}le"), "\u{fb01}le");
+
+        let nfkc = Options::new().normalization(Some(Form::Nfkc));
+        assert_eq!(normalized(&nfkc, "\u{fb01}le ２"), "file 2");
+    }
+
+    #[test]
+    fn diacritics_are_stripped_from_either_form() {
+        let options = Options::new().strip_diacritics(true);
+        assert_eq!(normalized(&options, "Crème brûlée"), "Creme brulee");
+        assert_eq!(normalized(&options, "cre\u{300}me"), "creme");
+        // Letters that are not a base plus a mark are kept.
+        assert_eq!(normalized(&options, "Øresund straße"), "Øresund straße");
+
+        let both = options.ignore_case(true);
+        assert_eq!(normalized(&both, "ÉCOLE école"), "ecole ecole");
+    }
+}
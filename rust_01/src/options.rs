@@ -0,0 +1,266 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use regex::RegexSet;
+
+use crate::counter::Unit;
+use crate::normalize::Form;
+use crate::stem::Stemmer;
+use crate::tokenizer::Tokenizer;
+
+/// What to count and how the text is prepared before counting.
+///
+/// ```
+/// use wordfreq::{Options, Tokenizer, WordCounter};
+///
+/// let options = Options::new()
+///     .ignore_case(true)
+///     .min_length(2)
+///     .tokenizer(Tokenizer::Unicode);
+/// let mut counter = WordCounter::new(&options);
+/// counter.feed("A rose is a rose");
+/// counter.finish();
+/// assert_eq!(counter.counts()["rose"], 2);
+/// ```
+#[derive(Clone)]
+pub struct Options {
+    pub(crate) min_length: usize,
+    pub(crate) ignore_case: bool,
+    pub(crate) case_fold: bool,
+    pub(crate) normalization: Option<Form>,
+    pub(crate) strip_diacritics: bool,
+    pub(crate) tokenizer: Tokenizer,
+    pub(crate) stop_words: Arc<HashSet<String>>,
+    pub(crate) include: Option<RegexSet>,
+    pub(crate) exclude: Option<RegexSet>,
+    pub(crate) stemmer: Option<Stemmer>,
+    pub(crate) surface_forms: bool,
+    pub(crate) ngram: usize,
+    pub(crate) sentence_boundaries: bool,
+    pub(crate) unit: Unit,
+    pub(crate) approximate: Option<usize>,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Self {
+            min_length: 1,
+            ignore_case: false,
+            case_fold: false,
+            normalization: None,
+            strip_diacritics: false,
+            tokenizer: Tokenizer::Simple,
+            stop_words: Arc::new(HashSet::new()),
+            include: None,
+            exclude: None,
+            stemmer: None,
+            surface_forms: false,
+            ngram: 1,
+            sentence_boundaries: false,
+            unit: Unit::Word,
+            approximate: None,
+        }
+    }
+
+    /// Ignore words shorter than `n`, in bytes for the simple tokenizer and
+    /// in grapheme clusters for the Unicode one.
+    pub fn min_length(mut self, n: usize) -> Self {
+        self.min_length = n;
+        self
+    }
+
+    /// Lowercase the text before counting.
+    pub fn ignore_case(mut self, yes: bool) -> Self {
+        self.ignore_case = yes;
+        self
+    }
+
+    /// Apply full Unicode case folding, so "Straße" and "STRASSE" match.
+    pub fn case_fold(mut self, yes: bool) -> Self {
+        self.case_fold = yes;
+        self
+    }
+
+    pub fn normalization(mut self, form: Option<Form>) -> Self {
+        self.normalization = form;
+        self
+    }
+
+    pub fn strip_diacritics(mut self, yes: bool) -> Self {
+        self.strip_diacritics = yes;
+        self
+    }
+
+    pub fn tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// Words that are never counted. They are normalized the same way as
+    /// the text, so a lowercase list also works with `ignore_case`.
+    pub fn stop_words<I: IntoIterator<Item = String>>(mut self, words: I) -> Self {
+        self.stop_words = Arc::new(words.into_iter().collect());
+        self
+    }
+
+    /// Only count words matched by one of the patterns. Like stop words,
+    /// the filters see words after normalization.
+    pub fn include(mut self, patterns: Option<RegexSet>) -> Self {
+        self.include = patterns;
+        self
+    }
+
+    /// Never count words matched by one of the patterns.
+    pub fn exclude(mut self, patterns: Option<RegexSet>) -> Self {
+        self.exclude = patterns;
+        self
+    }
+
+    /// Count words by their stem. Words are stemmed after case handling
+    /// and filtering, so stop words and filters see the words as written.
+    pub fn stemmer(mut self, stemmer: Option<Stemmer>) -> Self {
+        self.stemmer = stemmer;
+        self
+    }
+
+    /// Remember how often each stem was written in each form, for
+    /// [`WordCounter::surface_form`].
+    ///
+    /// [`WordCounter::surface_form`]: crate::WordCounter::surface_form
+    pub fn surface_forms(mut self, yes: bool) -> Self {
+        self.surface_forms = yes;
+        self
+    }
+
+    /// Count sequences of `n` adjacent words instead of single words.
+    pub fn ngram(mut self, n: usize) -> Self {
+        self.ngram = n.max(1);
+        self
+    }
+
+    /// Do not form n-grams across sentence-ending punctuation.
+    pub fn sentence_boundaries(mut self, yes: bool) -> Self {
+        self.sentence_boundaries = yes;
+        self
+    }
+
+    pub fn unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Track at most `capacity` distinct words with the Space-Saving
+    /// algorithm instead of counting every word exactly. Counts of the
+    /// tracked words are upper bounds; see [`WordCounter::error`].
+    ///
+    /// [`WordCounter::error`]: crate::WordCounter::error
+    pub fn approximate(mut self, capacity: Option<usize>) -> Self {
+        self.approximate = capacity;
+        self
+    }
+
+    /// Checks for combinations of options that cannot be counted.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.ngram > 1 && self.unit != Unit::Word {
+            return Err("n-grams can only be counted with the word unit".to_string());
+        }
+        let patterns = matches!(self.tokenizer, Tokenizer::Pattern(_))
+            || self.include.is_some()
+            || self.exclude.is_some();
+        if patterns && self.unit != Unit::Word {
+            return Err("token patterns and filters need the word unit".to_string());
+        }
+        if self.stemmer.is_some() && self.unit != Unit::Word {
+            return Err("stemming needs the word unit".to_string());
+        }
+        if self.surface_forms && self.stemmer.is_none() {
+            return Err("surface forms are only kept when stemming".to_string());
+        }
+        if self.surface_forms && self.approximate.is_some() {
+            return Err("surface forms cannot be kept with approximate counting".to_string());
+        }
+        if self.approximate == Some(0) {
+            return Err("approximate counting needs room for at least one word".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether segments cut by a [`Segmenter`](crate::Segmenter) can be
+    /// counted by separate counters and merged. N-grams need the words
+    /// before each segment, and bytes are not cut into text segments.
+    pub fn splittable(&self) -> bool {
+        self.ngram == 1 && self.unit != Unit::Byte
+    }
+
+    /// Describes every option that changes what gets counted, as
+    /// space-separated `name=value` pairs. Saved counts can only be merged
+    /// with counts taken under the same signature.
+    pub(crate) fn signature(&self) -> String {
+        let unit = match self.unit {
+            Unit::Word => "word",
+            Unit::Char => "char",
+            Unit::Grapheme => "grapheme",
+            Unit::Byte => "byte",
+        };
+        let tokenizer = match &self.tokenizer {
+            Tokenizer::Simple => "simple".to_string(),
+            Tokenizer::Unicode => "unicode".to_string(),
+            Tokenizer::Pattern(pattern) => {
+                format!("pattern:{:016x}", fingerprint(&[pattern.as_str()]))
+            }
+        };
+        let patterns = |set: &Option<RegexSet>| match set {
+            Some(set) => format!("{:016x}", fingerprint(set.patterns())),
+            None => "none".to_string(),
+        };
+        let normalize = match self.normalization {
+            None => "none",
+            Some(Form::Nfc) => "nfc",
+            Some(Form::Nfkc) => "nfkc",
+        };
+
+        let mut stop_words: Vec<&str> = self.stop_words.iter().map(String::as_str).collect();
+        stop_words.sort_unstable();
+
+        format!(
+            "unit={} tokenizer={} min_length={} ignore_case={} case_fold={} normalize={} \
+             strip_diacritics={} ngram={} sentence_boundaries={} stop_words={:016x} \
+             include={} exclude={} stem={}",
+            unit,
+            tokenizer,
+            self.min_length,
+            self.ignore_case,
+            self.case_fold,
+            normalize,
+            self.strip_diacritics,
+            self.ngram,
+            self.sentence_boundaries,
+            fingerprint(&stop_words),
+            patterns(&self.include),
+            patterns(&self.exclude),
+            self.stemmer.map_or("none", Stemmer::code)
+        )
+    }
+}
+
+// FNV-1a over the lines, so lists and patterns keep the signature short.
+fn fingerprint<S: AsRef<str>>(lines: &[S]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for (index, line) in lines.iter().enumerate() {
+        if index > 0 {
+            hash ^= b'\n' as u64;
+            hash = hash.wrapping_mul(0x100_0000_01b3);
+        }
+        for byte in line.as_ref().bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100_0000_01b3);
+        }
+    }
+    hash
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self::new()
+    }
+}
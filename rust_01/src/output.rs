@@ -0,0 +1,911 @@
+use std::mem;
+
+use serde_json::{json, Map, Value};
+use wordfreq::{
+    stats, Association, Category, CodeCounter, Collocations, CompareBy, Occurrence, TermScore,
+    TfIdf, WordChange, WordCounter,
+};
+
+use crate::{chart, format_number, Config};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+    Tsv,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            "tsv" => Some(Format::Tsv),
+            _ => None,
+        }
+    }
+}
+
+// Prints one report per input followed by the merged total, or a single
+// report when there is only one input. JSON wraps the reports in one
+// document, so the printer keeps them until the total.
+pub struct Printer<'a> {
+    config: &'a Config,
+    multiple: bool,
+    reports: usize,
+    columns_printed: bool,
+    files: Vec<Value>,
+    stats: Vec<(String, Vec<(&'static str, String)>)>,
+}
+
+impl<'a> Printer<'a> {
+    pub fn new(config: &'a Config, multiple: bool) -> Self {
+        Self {
+            config,
+            multiple,
+            reports: 0,
+            columns_printed: false,
+            files: Vec::new(),
+            stats: Vec::new(),
+        }
+    }
+
+    pub fn report(&mut self, label: &str, counter: &WordCounter) {
+        match self.config.format {
+            Format::Text => {
+                if self.multiple {
+                    println!("==> {} <==", label);
+                }
+                print_text(counter, self.config);
+                if self.multiple {
+                    println!();
+                }
+            }
+            Format::Json if self.multiple => {
+                self.files
+                    .push(json_report(Some(label), counter, self.config));
+            }
+            Format::Json => print_json(&json_report(None, counter, self.config)),
+            Format::Csv | Format::Tsv => self.print_rows(Some(label), counter),
+        }
+        self.reports += 1;
+    }
+
+    pub fn total(&mut self, counter: &WordCounter) {
+        let inputs = self.reports;
+        match self.config.format {
+            Format::Text => {
+                println!("==> Total ({} inputs) <==", inputs);
+                print_text(counter, self.config);
+            }
+            Format::Json => print_json(&json!({
+                "files": mem::take(&mut self.files),
+                "files_counted": inputs,
+                "total": json_report(None, counter, self.config),
+            })),
+            Format::Csv | Format::Tsv => {
+                self.print_rows(Some("<total>"), counter);
+                self.print_stats();
+            }
+        }
+    }
+
+    pub fn single(&mut self, counter: &WordCounter) {
+        match self.config.format {
+            Format::Csv | Format::Tsv => {
+                self.print_rows(None, counter);
+                self.print_stats();
+            }
+            _ => self.report("", counter),
+        }
+    }
+
+    fn print_rows(&mut self, label: Option<&str>, counter: &WordCounter) {
+        let total_tokens = counter.total();
+        let label = label.filter(|_| self.multiple);
+        // An approximate counter only knows the words it still tracks.
+        let distinct = if counter.is_approximate() {
+            "tracked_words"
+        } else {
+            "distinct_words"
+        };
+
+        // Metadata goes to stderr, so the output is plain CSV or TSV.
+        if self.config.header {
+            match label {
+                Some(label) => eprintln!(
+                    "# file: {}, total_tokens: {}, {}: {}",
+                    label,
+                    total_tokens,
+                    distinct,
+                    counter.distinct()
+                ),
+                None => eprintln!(
+                    "# total_tokens: {}, {}: {}",
+                    total_tokens,
+                    distinct,
+                    counter.distinct()
+                ),
+            }
+        }
+        if self.config.stats {
+            let label = label.unwrap_or_default().to_string();
+            self.stats.push((label, stats_rows(counter)));
+        }
+
+        if self.config.header && !self.columns_printed {
+            let mut columns = vec!["rank", "word", "count", "frequency"];
+            if counter.is_approximate() {
+                columns.push("error");
+            }
+            if self.config.surface_forms {
+                columns.push("surface");
+            }
+            if self.multiple {
+                columns.insert(0, "file");
+            }
+            print_row(self.config.format, &columns);
+            self.columns_printed = true;
+        }
+
+        for entry in counter.ranked().take(self.config.top) {
+            let rank = entry.rank.to_string();
+            let count = entry.count.to_string();
+            let frequency = relative_frequency(entry.count, total_tokens).to_string();
+            let error = counter.error(entry.word).to_string();
+            let mut fields = vec![rank.as_str(), entry.word, &count, &frequency];
+            if counter.is_approximate() {
+                fields.push(&error);
+            }
+            if self.config.surface_forms {
+                fields.push(counter.surface_form(entry.word).unwrap_or(entry.word));
+            }
+            if let Some(label) = label {
+                fields.insert(0, label);
+            }
+            print_row(self.config.format, &fields);
+        }
+    }
+
+    fn print_stats(&mut self) {
+        let key = if self.multiple { Some("file") } else { None };
+        print_stats_table(self.config, key, &mem::take(&mut self.stats));
+    }
+}
+
+fn print_row(format: Format, fields: &[&str]) {
+    let row: Vec<String> = match format {
+        Format::Tsv => fields.iter().map(|f| tsv_field(f)).collect(),
+        _ => fields.iter().map(|f| csv_field(f)).collect(),
+    };
+    let separator = if format == Format::Tsv { "\t" } else { "," };
+    println!("{}", row.join(separator));
+}
+
+pub fn print_comparison(
+    config: &Config,
+    labels: [&str; 2],
+    counters: [&WordCounter; 2],
+    by: CompareBy,
+    changes: &[WordChange],
+) {
+    let (by_name, keyness_name) = match by {
+        CompareBy::Change => ("change", None),
+        CompareBy::Relative => ("relative", None),
+        CompareBy::LogLikelihood => ("ll", Some("LL")),
+        CompareBy::ChiSquared => ("chi2", Some("chi2")),
+    };
+    let changes = &changes[..changes.len().min(config.top)];
+
+    match config.format {
+        Format::Text => {
+            println!("Comparing {} -> {} (by {}):", labels[0], labels[1], by_name);
+            for c in changes {
+                let mut line = format!(
+                    "{}: {} -> {} ({:+}, log ratio {:+.2}",
+                    c.word,
+                    format_number(c.before),
+                    format_number(c.after),
+                    c.change,
+                    c.log_ratio
+                );
+                if let (Some(name), Some(score)) = (keyness_name, c.keyness) {
+                    line += &format!(", {} {:.2}", name, score);
+                }
+                println!("{})", line);
+            }
+        }
+        Format::Json => {
+            let side = |label: &str, counter: &WordCounter| {
+                json!({
+                    "file": label,
+                    "total_tokens": counter.total(),
+                    "distinct_words": counter.distinct(),
+                })
+            };
+            let words: Vec<Value> = changes
+                .iter()
+                .enumerate()
+                .map(|(index, c)| {
+                    let mut word = json!({
+                        "rank": index + 1,
+                        "word": c.word,
+                        "before": c.before,
+                        "after": c.after,
+                        "change": c.change,
+                        "log_ratio": c.log_ratio,
+                    });
+                    if let Some(score) = c.keyness {
+                        word["keyness"] = score.into();
+                    }
+                    word
+                })
+                .collect();
+            print_json(&json!({
+                "before": side(labels[0], counters[0]),
+                "after": side(labels[1], counters[1]),
+                "ranked_by": by_name,
+                "words": words,
+            }));
+        }
+        Format::Csv | Format::Tsv => {
+            let mut columns = vec!["rank", "word", "before", "after", "change", "log_ratio"];
+            if keyness_name.is_some() {
+                columns.push("keyness");
+            }
+            if config.header {
+                for (side, (label, counter)) in
+                    ["before", "after"].iter().zip(labels.iter().zip(counters))
+                {
+                    eprintln!(
+                        "# {}: {}, total_tokens: {}, distinct_words: {}",
+                        side,
+                        label,
+                        counter.total(),
+                        counter.distinct()
+                    );
+                }
+                print_row(config.format, &columns);
+            }
+            for (index, c) in changes.iter().enumerate() {
+                let mut fields = vec![
+                    (index + 1).to_string(),
+                    c.word.to_string(),
+                    c.before.to_string(),
+                    c.after.to_string(),
+                    c.change.to_string(),
+                    c.log_ratio.to_string(),
+                ];
+                if let Some(score) = c.keyness {
+                    fields.push(score.to_string());
+                }
+                let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+                print_row(config.format, &fields);
+            }
+        }
+    }
+}
+
+// Prints the top terms of every document, then of the whole collection
+// labeled as "<all>".
+pub fn print_tfidf(config: &Config, tfidf: &TfIdf, documents: &[(String, WordCounter)]) {
+    let collection = tfidf.score_collection();
+    let collection_tokens: usize = documents.iter().map(|(_, counter)| counter.total()).sum();
+
+    match config.format {
+        Format::Text => {
+            let heading = if config.top_specified {
+                format!("Top {} terms by TF-IDF:", config.top)
+            } else {
+                "TF-IDF:".to_string()
+            };
+            let print_scores = |scores: &[TermScore]| {
+                println!("{}", heading);
+                for score in scores.iter().take(config.top) {
+                    println!(
+                        "{}: {:.4} (count {})",
+                        score.word,
+                        score.tfidf,
+                        format_number(score.count)
+                    );
+                }
+            };
+
+            for (label, counter) in documents {
+                println!("==> {} <==", label);
+                print_scores(&tfidf.score(counter));
+                println!();
+            }
+            println!("==> All documents ({}) <==", tfidf.documents());
+            print_scores(&collection);
+        }
+        Format::Json => {
+            let json_scores = |scores: &[TermScore]| -> Vec<Value> {
+                scores
+                    .iter()
+                    .take(config.top)
+                    .map(|score| {
+                        json!({
+                            "rank": score.rank,
+                            "word": score.word,
+                            "count": score.count,
+                            "tf": score.tf,
+                            "idf": score.idf,
+                            "tfidf": score.tfidf,
+                        })
+                    })
+                    .collect()
+            };
+            let documents: Vec<Value> = documents
+                .iter()
+                .map(|(label, counter)| {
+                    json!({
+                        "document": label,
+                        "total_tokens": counter.total(),
+                        "terms": json_scores(&tfidf.score(counter)),
+                    })
+                })
+                .collect();
+            print_json(&json!({
+                "documents": documents,
+                "documents_counted": tfidf.documents(),
+                "all": {
+                    "total_tokens": collection_tokens,
+                    "terms": json_scores(&collection),
+                },
+            }));
+        }
+        Format::Csv | Format::Tsv => {
+            if config.header {
+                eprintln!(
+                    "# documents: {}, total_tokens: {}",
+                    tfidf.documents(),
+                    collection_tokens
+                );
+                print_row(
+                    config.format,
+                    &["document", "rank", "word", "count", "tf", "idf", "tfidf"],
+                );
+            }
+            let print_scores = |label: &str, scores: &[TermScore]| {
+                for score in scores.iter().take(config.top) {
+                    let fields = [
+                        score.rank.to_string(),
+                        score.count.to_string(),
+                        score.tf.to_string(),
+                        score.idf.to_string(),
+                        score.tfidf.to_string(),
+                    ];
+                    print_row(
+                        config.format,
+                        &[
+                            label, &fields[0], score.word, &fields[1], &fields[2], &fields[3],
+                            &fields[4],
+                        ],
+                    );
+                }
+            };
+
+            for (label, counter) in documents {
+                print_scores(label, &tfidf.score(counter));
+            }
+            print_scores("<all>", &collection);
+        }
+    }
+}
+
+// Prints one ranking per category of code tokens.
+pub fn print_code(config: &Config, code: &CodeCounter, files: usize) {
+    match config.format {
+        Format::Text => {
+            for (index, category) in Category::ALL.into_iter().enumerate() {
+                if index > 0 {
+                    println!();
+                }
+                println!("==> {} ({} inputs) <==", category.name(), files);
+                print_text(code.counter(category), config);
+            }
+        }
+        Format::Json => {
+            let categories: Map<String, Value> = Category::ALL
+                .into_iter()
+                .map(|category| {
+                    let report = json_report(None, code.counter(category), config);
+                    (category.name().to_string(), report)
+                })
+                .collect();
+            print_json(&json!({
+                "files_counted": files,
+                "categories": categories,
+            }));
+        }
+        Format::Csv | Format::Tsv => {
+            let approximate = code.counter(Category::Identifier).is_approximate();
+            if config.header {
+                eprintln!("# files_counted: {}", files);
+            }
+            for category in Category::ALL {
+                let counter = code.counter(category);
+                if config.header {
+                    eprintln!(
+                        "# category: {}, total_tokens: {}, distinct_words: {}",
+                        category.name(),
+                        counter.total(),
+                        counter.distinct()
+                    );
+                }
+            }
+
+            if config.header {
+                let mut columns = vec!["category", "rank", "word", "count", "frequency"];
+                if approximate {
+                    columns.push("error");
+                }
+                print_row(config.format, &columns);
+            }
+            for category in Category::ALL {
+                let counter = code.counter(category);
+                for entry in counter.ranked().take(config.top) {
+                    let rank = entry.rank.to_string();
+                    let count = entry.count.to_string();
+                    let frequency = relative_frequency(entry.count, counter.total()).to_string();
+                    let error = counter.error(entry.word).to_string();
+                    let mut fields = vec![
+                        category.name(),
+                        rank.as_str(),
+                        entry.word,
+                        &count,
+                        &frequency,
+                    ];
+                    if approximate {
+                        fields.push(&error);
+                    }
+                    print_row(config.format, &fields);
+                }
+            }
+
+            if config.stats {
+                let stats: Vec<_> = Category::ALL
+                    .into_iter()
+                    .map(|category| {
+                        let counter = code.counter(category);
+                        (category.name().to_string(), stats_rows(counter))
+                    })
+                    .collect();
+                print_stats_table(config, Some("category"), &stats);
+            }
+        }
+    }
+}
+
+pub fn print_collocations(config: &Config, found: &Collocations, by: Association) {
+    let ranked = found.ranked(by, config.min_pair_count, config.min_word_count);
+
+    match config.format {
+        Format::Text => {
+            if config.top_specified {
+                println!("Top {} collocations by {}:", config.top, by.name());
+            } else {
+                println!("Collocations by {}:", by.name());
+            }
+            for collocation in ranked.iter().take(config.top) {
+                let score = match by {
+                    Association::Pmi => collocation.pmi,
+                    Association::TScore => collocation.t_score,
+                    Association::LogLikelihood => collocation.log_likelihood,
+                };
+                println!(
+                    "{} {}: {:.4} (count {})",
+                    collocation.first,
+                    collocation.second,
+                    score,
+                    format_number(collocation.count)
+                );
+            }
+        }
+        Format::Json => {
+            let collocations: Vec<Value> = ranked
+                .iter()
+                .take(config.top)
+                .map(|collocation| {
+                    json!({
+                        "rank": collocation.rank,
+                        "first": collocation.first,
+                        "second": collocation.second,
+                        "count": collocation.count,
+                        "pmi": collocation.pmi,
+                        "t_score": collocation.t_score,
+                        "log_likelihood": collocation.log_likelihood,
+                    })
+                })
+                .collect();
+            print_json(&json!({
+                "measure": by.name(),
+                "span": config.span,
+                "total_tokens": found.total(),
+                "total_pairs": found.pair_total(),
+                "collocations": collocations,
+            }));
+        }
+        Format::Csv | Format::Tsv => {
+            if config.header {
+                eprintln!(
+                    "# span: {}, total_tokens: {}, total_pairs: {}",
+                    config.span,
+                    found.total(),
+                    found.pair_total()
+                );
+                print_row(
+                    config.format,
+                    &[
+                        "rank",
+                        "first",
+                        "second",
+                        "count",
+                        "pmi",
+                        "t_score",
+                        "log_likelihood",
+                    ],
+                );
+            }
+            for collocation in ranked.iter().take(config.top) {
+                let fields = [
+                    collocation.rank.to_string(),
+                    collocation.count.to_string(),
+                    collocation.pmi.to_string(),
+                    collocation.t_score.to_string(),
+                    collocation.log_likelihood.to_string(),
+                ];
+                print_row(
+                    config.format,
+                    &[
+                        &fields[0],
+                        collocation.first,
+                        collocation.second,
+                        &fields[1],
+                        &fields[2],
+                        &fields[3],
+                        &fields[4],
+                    ],
+                );
+            }
+        }
+    }
+}
+
+// Prints occurrences as they are found, in grep style for text, so long
+// listings start right away. JSON occurrences are written one by one into
+// the document too.
+pub struct KwicPrinter<'a> {
+    config: &'a Config,
+    multiple: bool,
+    printed: usize,
+}
+
+impl<'a> KwicPrinter<'a> {
+    pub fn new(config: &'a Config, multiple: bool) -> Self {
+        match config.format {
+            Format::Json => println!("{{\n  \"occurrences\": ["),
+            Format::Csv | Format::Tsv if config.header => print_row(
+                config.format,
+                &["file", "line", "column", "key", "left", "word", "right"],
+            ),
+            _ => {}
+        }
+        Self {
+            config,
+            multiple,
+            printed: 0,
+        }
+    }
+
+    pub fn occurrence(&mut self, label: &str, line: usize, occurrence: &Occurrence) {
+        match self.config.format {
+            Format::Text => {
+                let location = if self.multiple {
+                    format!("{}:{}", label, line)
+                } else {
+                    line.to_string()
+                };
+                let padding = self
+                    .config
+                    .context
+                    .saturating_sub(occurrence.left.chars().count());
+                let (start, end) = if self.config.color {
+                    ("\x1b[1;31m", "\x1b[0m")
+                } else {
+                    ("", "")
+                };
+                println!(
+                    "{}: {}{}{}{}{}{}",
+                    location,
+                    " ".repeat(padding),
+                    occurrence.left,
+                    start,
+                    occurrence.word,
+                    end,
+                    occurrence.right
+                );
+            }
+            Format::Json => {
+                if self.printed > 0 {
+                    println!(",");
+                }
+                let occurrence = json!({
+                    "file": label,
+                    "line": line,
+                    "column": occurrence.column,
+                    "key": occurrence.key,
+                    "left": occurrence.left,
+                    "word": occurrence.word,
+                    "right": occurrence.right,
+                });
+                print!("    {}", occurrence);
+            }
+            Format::Csv | Format::Tsv => {
+                let line = line.to_string();
+                let column = occurrence.column.to_string();
+                print_row(
+                    self.config.format,
+                    &[
+                        label,
+                        &line,
+                        &column,
+                        &occurrence.key,
+                        occurrence.left,
+                        occurrence.word,
+                        occurrence.right,
+                    ],
+                );
+            }
+        }
+        self.printed += 1;
+    }
+
+    pub fn finish(self) {
+        if self.config.format == Format::Json {
+            if self.printed > 0 {
+                println!();
+            }
+            println!("  ],\n  \"total\": {}\n}}", self.printed);
+        }
+    }
+}
+
+fn print_text(counter: &WordCounter, config: &Config) {
+    let approximate = if counter.is_approximate() {
+        " (approximate)"
+    } else {
+        ""
+    };
+    if config.top_specified {
+        println!("Top {} words{}:", config.top, approximate);
+    } else {
+        println!("Word frequency{}:", approximate);
+    }
+
+    if config.chart {
+        chart::print_bars(counter, config);
+    } else {
+        for entry in counter.ranked().take(config.top) {
+            if let Some(surface) = counter.surface_form(entry.word) {
+                println!(
+                    "{}: {} ({})",
+                    entry.word,
+                    format_number(entry.count),
+                    surface
+                );
+                continue;
+            }
+            match counter.error(entry.word) {
+                0 => println!("{}: {}", entry.word, format_number(entry.count)),
+                error => println!(
+                    "{}: ~{} (at least {})",
+                    entry.word,
+                    format_number(entry.count),
+                    format_number(entry.count - error)
+                ),
+            }
+        }
+    }
+
+    if config.stats {
+        print_stats(counter);
+    }
+}
+
+fn print_stats(counter: &WordCounter) {
+    let stats = stats(counter);
+
+    println!();
+    println!("Statistics:");
+    println!("Total tokens: {}", format_number(stats.total_tokens));
+    println!("Distinct words: {}", format_number(stats.distinct_words));
+    println!("Type/token ratio: {:.4}", stats.type_token_ratio);
+    println!(
+        "Hapax legomena: {} ({:.1}% of distinct words)",
+        format_number(stats.hapax_legomena),
+        100.0 * relative_frequency(stats.hapax_legomena, stats.distinct_words)
+    );
+    println!("Average word length: {:.2}", stats.average_length);
+    if let Some(sentences) = stats.sentences {
+        println!("Sentences: {}", format_number(sentences));
+    }
+    match stats.zipf {
+        Some(zipf) => println!(
+            "Zipf fit: slope {:.3}, R² {:.3}",
+            zipf.slope, zipf.r_squared
+        ),
+        None => println!("Zipf fit: not enough distinct words"),
+    }
+}
+
+// The statistics as they are listed in CSV and TSV, by name.
+fn stats_rows(counter: &WordCounter) -> Vec<(&'static str, String)> {
+    let stats = stats(counter);
+    let mut rows = vec![
+        ("total_tokens", stats.total_tokens.to_string()),
+        ("distinct_words", stats.distinct_words.to_string()),
+        ("type_token_ratio", stats.type_token_ratio.to_string()),
+        ("hapax_legomena", stats.hapax_legomena.to_string()),
+        ("average_word_length", stats.average_length.to_string()),
+    ];
+    if let Some(sentences) = stats.sentences {
+        rows.push(("sentences", sentences.to_string()));
+    }
+    if let Some(zipf) = stats.zipf {
+        rows.push(("zipf_slope", zipf.slope.to_string()));
+        rows.push(("zipf_intercept", zipf.intercept.to_string()));
+        rows.push(("zipf_r_squared", zipf.r_squared.to_string()));
+    }
+    rows
+}
+
+// Prints the statistics of every report as a second table after the
+// words, separated by an empty line. `key` names the column that tells
+// the reports apart, if there is more than one.
+fn print_stats_table(
+    config: &Config,
+    key: Option<&str>,
+    reports: &[(String, Vec<(&str, String)>)],
+) {
+    if reports.is_empty() {
+        return;
+    }
+    println!();
+    if config.header {
+        let mut columns = vec!["statistic", "value"];
+        if let Some(key) = key {
+            columns.insert(0, key);
+        }
+        print_row(config.format, &columns);
+    }
+    for (label, rows) in reports {
+        for (name, value) in rows {
+            let mut fields = vec![*name, value.as_str()];
+            if key.is_some() {
+                fields.insert(0, label);
+            }
+            print_row(config.format, &fields);
+        }
+    }
+}
+
+fn json_stats(counter: &WordCounter) -> Value {
+    let stats = stats(counter);
+    json!({
+        "type_token_ratio": stats.type_token_ratio,
+        "hapax_legomena": stats.hapax_legomena,
+        "average_word_length": stats.average_length,
+        "sentences": stats.sentences,
+        "zipf": stats.zipf.map(|zipf| json!({
+            "slope": zipf.slope,
+            "intercept": zipf.intercept,
+            "r_squared": zipf.r_squared,
+        })),
+    })
+}
+
+fn json_report(label: Option<&str>, counter: &WordCounter, config: &Config) -> Value {
+    let total_tokens = counter.total();
+    let mut report = Map::new();
+
+    if let Some(label) = label {
+        report.insert("file".to_string(), label.into());
+    }
+    report.insert("total_tokens".to_string(), total_tokens.into());
+    if counter.is_approximate() {
+        report.insert("approximate".to_string(), true.into());
+        report.insert("tracked_words".to_string(), counter.distinct().into());
+    } else {
+        report.insert("distinct_words".to_string(), counter.distinct().into());
+    }
+    if config.stats {
+        report.insert("stats".to_string(), json_stats(counter));
+    }
+
+    let words: Vec<Value> = counter
+        .ranked()
+        .take(config.top)
+        .map(|entry| {
+            let mut word = json!({
+                "rank": entry.rank,
+                "word": entry.word,
+                "count": entry.count,
+                "frequency": relative_frequency(entry.count, total_tokens),
+            });
+            if counter.is_approximate() {
+                word["error"] = counter.error(entry.word).into();
+            }
+            if let Some(surface) = counter.surface_form(entry.word) {
+                word["surface"] = surface.into();
+            }
+            word
+        })
+        .collect();
+    report.insert("words".to_string(), words.into());
+    Value::Object(report)
+}
+
+fn print_json(value: &Value) {
+    println!("{:#}", value);
+}
+
+fn relative_frequency(count: usize, total_tokens: usize) -> f64 {
+    if total_tokens == 0 {
+        0.0
+    } else {
+        count as f64 / total_tokens as f64
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) || s.starts_with(' ') || s.ends_with(' ') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn tsv_field(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("word"), "word");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+        assert_eq!(csv_field(" padded"), "\" padded\"");
+        assert_eq!(csv_field("padded "), "\"padded \"");
+        assert_eq!(csv_field("in side"), "in side");
+        assert_eq!(csv_field("tab\there"), "tab\there");
+    }
+
+    #[test]
+    fn tsv_fields_escape_separators() {
+        assert_eq!(tsv_field("word"), "word");
+        assert_eq!(tsv_field("tab\there"), "tab\\there");
+        assert_eq!(tsv_field("line\r\nbreak"), "line\\r\\nbreak");
+        assert_eq!(tsv_field("back\\slash"), "back\\\\slash");
+        assert_eq!(tsv_field(" \"a,b\" "), " \"a,b\" ");
+    }
+}
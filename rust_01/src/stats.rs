@@ -0,0 +1,126 @@
+use crate::WordCounter;
+
+/// Summary statistics of a counted text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stats {
+    pub total_tokens: usize,
+    pub distinct_words: usize,
+    /// Distinct words divided by total tokens.
+    pub type_token_ratio: f64,
+    /// Words that occur exactly once.
+    pub hapax_legomena: usize,
+    /// Mean length of the counted tokens in characters.
+    pub average_length: f64,
+    pub sentences: Option<usize>,
+    /// Least-squares fit of `ln(count)` against `ln(rank)`; `None` with
+    /// fewer than two distinct words.
+    pub zipf: Option<ZipfFit>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ZipfFit {
+    /// Zipf's law predicts a slope close to -1.
+    pub slope: f64,
+    pub intercept: f64,
+    pub r_squared: f64,
+}
+
+/// Computes the statistics of a finished counter.
+pub fn stats(counter: &WordCounter) -> Stats {
+    let total_tokens = counter.total();
+    let distinct_words = counter.distinct();
+
+    let mut hapax_legomena = 0;
+    let mut characters = 0;
+    let mut points = Vec::with_capacity(distinct_words);
+    for entry in counter.ranked() {
+        if entry.count == 1 {
+            hapax_legomena += 1;
+        }
+        characters += entry.word.chars().count() * entry.count;
+        points.push(((entry.rank as f64).ln(), (entry.count as f64).ln()));
+    }
+
+    Stats {
+        total_tokens,
+        distinct_words,
+        type_token_ratio: ratio(distinct_words, total_tokens),
+        hapax_legomena,
+        average_length: ratio(characters, total_tokens),
+        sentences: counter.sentences(),
+        zipf: fit(&points),
+    }
+}
+
+fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
+}
+
+fn fit(points: &[(f64, f64)]) -> Option<ZipfFit> {
+    if points.len() < 2 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+
+    let mut sxx = 0.0;
+    let mut sxy = 0.0;
+    let mut syy = 0.0;
+    for &(x, y) in points {
+        sxx += (x - mean_x) * (x - mean_x);
+        sxy += (x - mean_x) * (y - mean_y);
+        syy += (y - mean_y) * (y - mean_y);
+    }
+
+    let slope = sxy / sxx;
+    // Equal counts lie exactly on the flat line.
+    let r_squared = if syy == 0.0 {
+        1.0
+    } else {
+        sxy * sxy / (sxx * syy)
+    };
+
+    Some(ZipfFit {
+        slope,
+        intercept: mean_y - slope * mean_x,
+        r_squared,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Options;
+
+    #[test]
+    fn counts_types_hapaxes_and_sentences() {
+        let mut counter = WordCounter::new(&Options::new());
+        counter.feed("a rose is a rose. is it");
+        counter.finish();
+
+        let stats = stats(&counter);
+        assert_eq!((stats.total_tokens, stats.distinct_words), (7, 4));
+        assert_eq!(stats.hapax_legomena, 1);
+        assert_eq!(stats.sentences, Some(2));
+        assert!((stats.average_length - 16.0 / 7.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn zipf_fit_recovers_an_exact_power_law() {
+        let points: Vec<(f64, f64)> = (1..=20)
+            .map(|rank| {
+                let rank = rank as f64;
+                (rank.ln(), (1000.0 / rank).ln())
+            })
+            .collect();
+        let zipf = fit(&points).unwrap();
+        assert!((zipf.slope + 1.0).abs() < 1e-9);
+        assert!((zipf.r_squared - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,74 @@
+use rust_stemmers::Algorithm;
+
+/// Snowball stemming algorithm applied to words before they are counted,
+/// so "run", "runs" and "running" are counted together.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Stemmer {
+    Danish,
+    Dutch,
+    English,
+    Finnish,
+    French,
+    German,
+    Italian,
+    Norwegian,
+    Portuguese,
+    Russian,
+    Spanish,
+    Swedish,
+}
+
+const LANGUAGES: &[(&str, &str, Stemmer)] = &[
+    ("da", "danish", Stemmer::Danish),
+    ("de", "german", Stemmer::German),
+    ("en", "english", Stemmer::English),
+    ("es", "spanish", Stemmer::Spanish),
+    ("fi", "finnish", Stemmer::Finnish),
+    ("fr", "french", Stemmer::French),
+    ("it", "italian", Stemmer::Italian),
+    ("nl", "dutch", Stemmer::Dutch),
+    ("no", "norwegian", Stemmer::Norwegian),
+    ("pt", "portuguese", Stemmer::Portuguese),
+    ("ru", "russian", Stemmer::Russian),
+    ("sv", "swedish", Stemmer::Swedish),
+];
+
+impl Stemmer {
+    /// Accepts a language code like the stop-word lists (`en`) or the
+    /// language's English name (`english`).
+    pub fn parse(s: &str) -> Option<Self> {
+        LANGUAGES
+            .iter()
+            .find(|(code, name, _)| *code == s || *name == s)
+            .map(|&(_, _, stemmer)| stemmer)
+    }
+
+    /// Codes of the supported languages.
+    pub fn languages() -> Vec<&'static str> {
+        LANGUAGES.iter().map(|(code, _, _)| *code).collect()
+    }
+
+    pub(crate) fn code(self) -> &'static str {
+        LANGUAGES
+            .iter()
+            .find(|(_, _, stemmer)| *stemmer == self)
+            .map_or("", |(code, _, _)| code)
+    }
+
+    pub(crate) fn create(self) -> rust_stemmers::Stemmer {
+        rust_stemmers::Stemmer::create(match self {
+            Stemmer::Danish => Algorithm::Danish,
+            Stemmer::Dutch => Algorithm::Dutch,
+            Stemmer::English => Algorithm::English,
+            Stemmer::Finnish => Algorithm::Finnish,
+            Stemmer::French => Algorithm::French,
+            Stemmer::German => Algorithm::German,
+            Stemmer::Italian => Algorithm::Italian,
+            Stemmer::Norwegian => Algorithm::Norwegian,
+            Stemmer::Portuguese => Algorithm::Portuguese,
+            Stemmer::Russian => Algorithm::Russian,
+            Stemmer::Spanish => Algorithm::Spanish,
+            Stemmer::Swedish => Algorithm::Swedish,
+        })
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+const BUILTIN: &[(&str, &str)] = &[
+    ("de", include_str!("stopwords/de.txt")),
+    ("en", include_str!("stopwords/en.txt")),
+    ("es", include_str!("stopwords/es.txt")),
+    ("fr", include_str!("stopwords/fr.txt")),
+    ("it", include_str!("stopwords/it.txt")),
+    ("nl", include_str!("stopwords/nl.txt")),
+    ("pt", include_str!("stopwords/pt.txt")),
+];
+
+/// Names of the bundled stop-word lists.
+pub fn languages() -> Vec<&'static str> {
+    BUILTIN.iter().map(|(lang, _)| *lang).collect()
+}
+
+/// Reads stop words from each spec, which is either the name of a bundled
+/// list or a file (one word per line, `#` starts a comment). Bundled names
+/// come first, so a file named like one is given as a path, e.g. `./en`.
+pub fn load(specs: &[String]) -> Result<HashSet<String>, String> {
+    let mut stop_words = HashSet::new();
+
+    for spec in specs {
+        let list = match BUILTIN.iter().find(|(lang, _)| lang == spec) {
+            Some((_, list)) => list.to_string(),
+            None if Path::new(spec).is_file() => {
+                fs::read_to_string(spec).map_err(|e| format!("{}: {}", spec, e))?
+            }
+            None => {
+                return Err(format!(
+                    "Unknown stop-word list {} (not a file or one of: {})",
+                    spec,
+                    languages().join(", ")
+                ))
+            }
+        };
+
+        for line in list.lines() {
+            let word = line.split('#').next().unwrap_or("").trim();
+            if !word.is_empty() {
+                stop_words.insert(word.to_string());
+            }
+        }
+    }
+
+    Ok(stop_words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_names_come_before_files() {
+        let dir = std::env::temp_dir().join(format!("wordfreq-stopwords-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("en");
+        fs::write(&file, "# mine\nfoo\nbar # trailing\n").unwrap();
+
+        let bundled = load(&["en".to_string()]).unwrap();
+        assert!(bundled.contains("the") && !bundled.contains("foo"));
+
+        let own = load(&[file.display().to_string()]).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(own, HashSet::from(["foo".to_string(), "bar".to_string()]));
+
+        let err = load(&["xx".to_string()]).unwrap_err();
+        assert!(err.starts_with("Unknown stop-word list xx"));
+    }
+}
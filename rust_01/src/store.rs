@@ -0,0 +1,201 @@
+//! Saving finished counts to disk and loading them back.
+//!
+//! A state file holds the counts of one [`WordCounter`] together with the
+//! signature of the [`Options`] they were taken with:
+//!
+//! ```text
+//! "WFDB" version:u8 signature:bytes sentences:varint entries:varint (word:bytes count:varint)*
+//! ```
+//!
+//! Integers are unsigned LEB128 varints and `bytes` is a varint length
+//! followed by UTF-8. Entries are sorted by word, so the same counts
+//! always produce the same file.
+
+use std::io::{self, Read, Write};
+
+use crate::{Options, WordCounter};
+
+const MAGIC: &[u8; 4] = b"WFDB";
+const VERSION: u8 = 1;
+
+// Longer words or signatures mean the file is corrupt, not that it holds
+// a very long word.
+const MAX_STRING: u64 = 1 << 24;
+
+/// Writes the counts of a finished `counter`, which was built with
+/// `options`. Approximate counts cannot be saved.
+pub fn save<W: Write>(counter: &WordCounter, options: &Options, mut writer: W) -> io::Result<()> {
+    if counter.is_approximate() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "approximate counts cannot be saved",
+        ));
+    }
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION])?;
+    write_bytes(&mut writer, options.signature().as_bytes())?;
+    write_varint(&mut writer, counter.sentences().unwrap_or(0) as u64)?;
+
+    let mut entries: Vec<(&String, &usize)> = counter.counts().iter().collect();
+    entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+    write_varint(&mut writer, entries.len() as u64)?;
+    for (word, &count) in entries {
+        write_bytes(&mut writer, word.as_bytes())?;
+        write_varint(&mut writer, count as u64)?;
+    }
+    writer.flush()
+}
+
+/// Reads saved counts into a new counter for `options`, which must match
+/// the options the counts were saved with. The counter can be merged with
+/// others and fed more text.
+pub fn load<R: Read>(mut reader: R, options: &Options) -> io::Result<WordCounter> {
+    let mut magic = [0; 5];
+    read_exact(&mut reader, &mut magic)?;
+    if &magic[..4] != MAGIC {
+        return Err(invalid("not a wordfreq state file".to_string()));
+    }
+    if magic[4] != VERSION {
+        return Err(invalid(format!(
+            "unsupported state file version {}",
+            magic[4]
+        )));
+    }
+
+    let saved = read_string(&mut reader)?;
+    let current = options.signature();
+    if saved != current {
+        let differences: Vec<String> = saved
+            .split(' ')
+            .zip(current.split(' '))
+            .filter(|(a, b)| a != b)
+            .map(|(a, b)| format!("{} (now {})", a, b))
+            .collect();
+        return Err(invalid(format!(
+            "counted with different options: {}",
+            differences.join(", ")
+        )));
+    }
+
+    let mut counter = WordCounter::new(options);
+    let sentences = read_varint(&mut reader)?;
+    counter.set_sentences(sentences as usize);
+    let entries = read_varint(&mut reader)?;
+    for _ in 0..entries {
+        let word = read_string(&mut reader)?;
+        let count = read_varint(&mut reader)?;
+        counter.add_count(word, count as usize);
+    }
+
+    if reader.read(&mut [0])? != 0 {
+        return Err(invalid("unexpected data after the last entry".to_string()));
+    }
+    Ok(counter)
+}
+
+fn write_varint<W: Write>(writer: &mut W, mut n: u64) -> io::Result<()> {
+    let mut buf = [0; 10];
+    let mut len = 0;
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+    writer.write_all(&buf[..len])
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    write_varint(writer, bytes.len() as u64)?;
+    writer.write_all(bytes)
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut n = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        read_exact(reader, &mut byte)?;
+        n |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(invalid("malformed number".to_string()))
+}
+
+fn read_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    let len = read_varint(reader)?;
+    if len > MAX_STRING {
+        return Err(invalid("malformed entry".to_string()));
+    }
+    let mut bytes = vec![0; len as usize];
+    read_exact(reader, &mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| invalid("entry is not valid UTF-8".to_string()))
+}
+
+fn read_exact<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<()> {
+    reader.read_exact(buf).map_err(|e| {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            invalid("truncated state file".to_string())
+        } else {
+            e
+        }
+    })
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counter(text: &str, options: &Options) -> WordCounter {
+        let mut counter = WordCounter::new(options);
+        counter.feed(text);
+        counter.finish();
+        counter
+    }
+
+    #[test]
+    fn round_trip_and_merge_match_a_single_count() {
+        let options = Options::new().ignore_case(true);
+        let mut saved = Vec::new();
+        save(
+            &counter("The cat. And the hat", &options),
+            &options,
+            &mut saved,
+        )
+        .unwrap();
+
+        let mut merged = load(&saved[..], &options).unwrap();
+        merged.merge(counter("the end", &options));
+
+        let single = counter("The cat. And the hat the end", &options);
+        assert_eq!(merged.counts(), single.counts());
+        // Each saved count ends its last sentence.
+        assert_eq!(merged.sentences(), Some(3));
+    }
+
+    #[test]
+    fn rejects_other_options_and_corrupt_files() {
+        let options = Options::new();
+        let mut saved = Vec::new();
+        save(&counter("a b", &options), &options, &mut saved).unwrap();
+
+        let err = load(&saved[..], &Options::new().ngram(2)).err().unwrap();
+        assert!(err.to_string().contains("ngram=1 (now ngram=2)"));
+
+        let err = load(&saved[..saved.len() - 1], &options).err().unwrap();
+        assert_eq!(err.to_string(), "truncated state file");
+        assert!(load(&b"hello"[..], &options).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::WordCounter;
+
+/// Document frequencies of a collection, built by adding one finished
+/// counter per document.
+pub struct TfIdf {
+    documents: usize,
+    document_frequency: HashMap<String, usize>,
+    collection: HashMap<String, usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TermScore<'a> {
+    pub rank: usize,
+    pub word: &'a str,
+    pub count: usize,
+    /// Count divided by the number of tokens in the document.
+    pub tf: f64,
+    pub idf: f64,
+    pub tfidf: f64,
+}
+
+impl TfIdf {
+    pub fn new() -> Self {
+        Self {
+            documents: 0,
+            document_frequency: HashMap::new(),
+            collection: HashMap::new(),
+        }
+    }
+
+    pub fn add_document(&mut self, document: &WordCounter) {
+        self.documents += 1;
+        for (word, count) in document.counts() {
+            *self.document_frequency.entry(word.clone()).or_insert(0) += 1;
+            *self.collection.entry(word.clone()).or_insert(0) += count;
+        }
+    }
+
+    pub fn documents(&self) -> usize {
+        self.documents
+    }
+
+    /// Smoothed inverse document frequency, `ln((1 + N) / (1 + df)) + 1`,
+    /// so a word found in every document still scores above zero.
+    pub fn idf(&self, word: &str) -> f64 {
+        let df = self.document_frequency.get(word).copied().unwrap_or(0);
+        ((1.0 + self.documents as f64) / (1.0 + df as f64)).ln() + 1.0
+    }
+
+    /// Ranks the words of one document of the collection.
+    pub fn score<'a>(&self, document: &'a WordCounter) -> Vec<TermScore<'a>> {
+        self.rank(document.counts())
+    }
+
+    /// Ranks the words of the whole collection, using their total count
+    /// over all documents as the term frequency.
+    pub fn score_collection(&self) -> Vec<TermScore<'_>> {
+        self.rank(&self.collection)
+    }
+
+    fn rank<'a>(&self, counts: &'a HashMap<String, usize>) -> Vec<TermScore<'a>> {
+        let total: usize = counts.values().sum();
+
+        let mut scores: Vec<TermScore> = counts
+            .iter()
+            .map(|(word, &count)| {
+                let tf = count as f64 / total as f64;
+                let idf = self.idf(word);
+                TermScore {
+                    rank: 0,
+                    word,
+                    count,
+                    tf,
+                    idf,
+                    tfidf: tf * idf,
+                }
+            })
+            .collect();
+
+        scores.sort_by(|a, b| {
+            b.tfidf
+                .partial_cmp(&a.tfidf)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.word.cmp(b.word))
+        });
+        for (index, score) in scores.iter_mut().enumerate() {
+            score.rank = index + 1;
+        }
+        scores
+    }
+}
+
+impl Default for TfIdf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Options;
+
+    fn document(text: &str) -> WordCounter {
+        let mut counter = WordCounter::new(&Options::new());
+        counter.feed(text);
+        counter.finish();
+        counter
+    }
+
+    #[test]
+    fn words_in_fewer_documents_rank_higher() {
+        let docs = [
+            document("the cat"),
+            document("the dog"),
+            document("the cat"),
+        ];
+        let mut tfidf = TfIdf::new();
+        for doc in &docs {
+            tfidf.add_document(doc);
+        }
+
+        let words: Vec<&str> = tfidf.score(&docs[1]).iter().map(|s| s.word).collect();
+        assert_eq!(words, ["dog", "the"]);
+        assert!((tfidf.idf("the") - 1.0).abs() < 1e-12);
+        assert!(tfidf.idf("dog") > tfidf.idf("cat"));
+    }
+
+    #[test]
+    fn collection_uses_total_counts() {
+        let docs = [document("a a b"), document("a c")];
+        let mut tfidf = TfIdf::new();
+        for doc in &docs {
+            tfidf.add_document(doc);
+        }
+
+        let scores = tfidf.score_collection();
+        let a = scores.iter().find(|s| s.word == "a").unwrap();
+        assert_eq!(a.count, 3);
+        assert!((a.tf - 0.6).abs() < 1e-12);
+    }
+}
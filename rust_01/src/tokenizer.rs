@@ -0,0 +1,121 @@
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// How text is split into words.
+#[derive(Clone)]
+pub enum Tokenizer {
+    /// Splits at anything that is not alphanumeric, `'` or `"`.
+    Simple,
+    /// Unicode word boundaries (UAX #29).
+    Unicode,
+    /// Every match of the pattern in a line of the normalized text is a
+    /// word; matches never span lines.
+    Pattern(Regex),
+}
+
+impl Tokenizer {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "simple" => Some(Tokenizer::Simple),
+            "unicode" => Some(Tokenizer::Unicode),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn words<'t>(&'t self, text: &'t str) -> Box<dyn Iterator<Item = &'t str> + 't> {
+        match self {
+            Tokenizer::Simple => Box::new(text.split(is_separator).filter(|w| !w.is_empty())),
+            Tokenizer::Unicode => Box::new(text.unicode_words()),
+            // Matched line by line, so where the stream was cut never matters.
+            Tokenizer::Pattern(pattern) => Box::new(
+                text.split('\n')
+                    .flat_map(|line| pattern.find_iter(line))
+                    .map(|m| m.as_str())
+                    .filter(|w| !w.is_empty()),
+            ),
+        }
+    }
+
+    /// Like [`words`](Self::words), with the byte offset of each word in
+    /// `text`.
+    pub(crate) fn word_indices<'t>(
+        &'t self,
+        text: &'t str,
+    ) -> impl Iterator<Item = (usize, &'t str)> + 't {
+        // Every word is a slice of `text`.
+        self.words(text)
+            .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+    }
+
+    // The simple splitter has always compared byte lengths; the other
+    // tokenizers count what a reader would see as characters.
+    pub(crate) fn word_length(&self, word: &str) -> usize {
+        match self {
+            Tokenizer::Simple => word.len(),
+            Tokenizer::Unicode | Tokenizer::Pattern(_) => word.graphemes(true).count(),
+        }
+    }
+
+    // Unicode word boundaries never fall inside a run of whitespace, and
+    // neither do the simple tokenizer's, so whitespace is always safe to cut
+    // at; the simple tokenizer can also cut at any of its separators. A
+    // pattern may match whitespace, so it is only cut at line ends.
+    pub(crate) fn is_chunk_boundary(&self, c: char) -> bool {
+        match self {
+            Tokenizer::Simple => is_separator(c),
+            Tokenizer::Unicode => c.is_whitespace(),
+            Tokenizer::Pattern(_) => c == '\n',
+        }
+    }
+}
+
+pub(crate) fn is_separator(c: char) -> bool {
+    !c.is_alphanumeric() && c != '\'' && c != '"'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_keeps_apostrophes_and_quotes_inside_words() {
+        let words: Vec<&str> = Tokenizer::Simple
+            .words("it's a \"quoted\" well-known fact")
+            .collect();
+        assert_eq!(words, ["it's", "a", "\"quoted\"", "well", "known", "fact"]);
+    }
+
+    #[test]
+    fn simple_skips_empty_words() {
+        let words: Vec<&str> = Tokenizer::Simple.words("  ,, a..b  ").collect();
+        assert_eq!(words, ["a", "b"]);
+    }
+
+    #[test]
+    fn unicode_follows_word_boundaries() {
+        let words: Vec<&str> = Tokenizer::Unicode.words("can’t stop 3.14 日本語").collect();
+        assert_eq!(words, ["can’t", "stop", "3.14", "日", "本", "語"]);
+    }
+
+    #[test]
+    fn pattern_matches_are_words() {
+        let tokenizer = Tokenizer::Pattern(Regex::new(r"#\w+|\d+(\.\d+){3}").unwrap());
+        let words: Vec<&str> = tokenizer
+            .words("ping 10.0.0.1 from #ops, not 1.2")
+            .collect();
+        assert_eq!(words, ["10.0.0.1", "#ops"]);
+    }
+
+    #[test]
+    fn word_indices_are_byte_offsets() {
+        let words: Vec<(usize, &str)> = Tokenizer::Unicode.word_indices("né, le bœuf").collect();
+        assert_eq!(words, [(0, "né"), (5, "le"), (8, "bœuf")]);
+    }
+
+    #[test]
+    fn word_length_is_bytes_or_graphemes() {
+        assert_eq!(Tokenizer::Simple.word_length("née"), 4);
+        assert_eq!(Tokenizer::Unicode.word_length("née"), 3);
+        assert_eq!(Tokenizer::Unicode.word_length("ne\u{301}e"), 3);
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::{BTreeSet, HashMap};
+
+// Space-Saving (Metwally et al.): tracks at most `capacity` words. A new
+// word takes over the slot of the least counted one and inherits its count
+// as error, so every tracked count is an overestimate by at most `error`,
+// and any word counted more often than the smallest tracked count is
+// guaranteed to be tracked.
+pub(crate) struct SpaceSaving {
+    capacity: usize,
+    index: HashMap<String, usize>,
+    slots: Vec<Slot>,
+    by_count: BTreeSet<(usize, usize)>,
+    total: usize,
+}
+
+struct Slot {
+    word: String,
+    count: usize,
+    error: usize,
+}
+
+impl SpaceSaving {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            index: HashMap::new(),
+            slots: Vec::new(),
+            by_count: BTreeSet::new(),
+            total: 0,
+        }
+    }
+
+    pub fn add(&mut self, word: &str, n: usize) {
+        self.total += n;
+
+        if let Some(&slot) = self.index.get(word) {
+            let count = self.slots[slot].count;
+            self.by_count.remove(&(count, slot));
+            self.slots[slot].count += n;
+            self.by_count.insert((count + n, slot));
+            return;
+        }
+
+        if self.slots.len() < self.capacity {
+            let slot = self.slots.len();
+            self.slots.push(Slot {
+                word: word.to_string(),
+                count: n,
+                error: 0,
+            });
+            self.index.insert(word.to_string(), slot);
+            self.by_count.insert((n, slot));
+            return;
+        }
+
+        let (min, slot) = self.by_count.pop_first().unwrap();
+        self.index.remove(&self.slots[slot].word);
+        self.slots[slot] = Slot {
+            word: word.to_string(),
+            count: min + n,
+            error: min,
+        };
+        self.index.insert(word.to_string(), slot);
+        self.by_count.insert((min + n, slot));
+    }
+
+    /// Combines two summaries. A word missing from a full summary may have
+    /// been counted up to its smallest count, so that much is added to both
+    /// its count and its error; then the largest counts are kept.
+    pub fn merge(&mut self, other: SpaceSaving) {
+        let floor = |sketch: &SpaceSaving| {
+            if sketch.slots.len() < sketch.capacity {
+                0
+            } else {
+                sketch.by_count.first().map_or(0, |&(count, _)| count)
+            }
+        };
+        let (self_floor, other_floor) = (floor(self), floor(&other));
+
+        let mut merged: HashMap<String, (usize, usize)> = HashMap::new();
+        for slot in &self.slots {
+            merged.insert(slot.word.clone(), (slot.count, slot.error));
+        }
+        for slot in &other.slots {
+            let entry = merged
+                .entry(slot.word.clone())
+                .or_insert((self_floor, self_floor));
+            entry.0 += slot.count;
+            entry.1 += slot.error;
+        }
+        for (word, entry) in merged.iter_mut() {
+            if !other.index.contains_key(word) {
+                entry.0 += other_floor;
+                entry.1 += other_floor;
+            }
+        }
+
+        let mut entries: Vec<(String, (usize, usize))> = merged.into_iter().collect();
+        entries.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then_with(|| a.0.cmp(&b.0)));
+        entries.truncate(self.capacity);
+
+        let total = self.total + other.total;
+        *self = SpaceSaving::new(self.capacity);
+        self.total = total;
+        for (word, (count, error)) in entries {
+            let slot = self.slots.len();
+            self.index.insert(word.clone(), slot);
+            self.by_count.insert((count, slot));
+            self.slots.push(Slot { word, count, error });
+        }
+    }
+
+    pub fn counts(&self) -> HashMap<String, usize> {
+        self.slots
+            .iter()
+            .map(|slot| (slot.word.clone(), slot.count))
+            .collect()
+    }
+
+    pub fn error(&self, word: &str) -> usize {
+        self.index
+            .get(word)
+            .map_or(0, |&slot| self.slots[slot].error)
+    }
+
+    /// Number of tokens added, tracked or not.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_frequent_words_with_bounded_error() {
+        let mut sketch = SpaceSaving::new(2);
+        for word in "a b a c a d a b".split(' ') {
+            sketch.add(word, 1);
+        }
+
+        let counts = sketch.counts();
+        assert_eq!(counts["a"], 4);
+        assert_eq!(sketch.error("a"), 0);
+        assert_eq!(sketch.total(), 8);
+        // "b" took over the last slot; its true count of 2 is within the error.
+        assert_eq!(counts["b"], 4);
+        assert_eq!(sketch.error("b"), 3);
+    }
+
+    #[test]
+    fn merge_of_unfilled_summaries_is_exact() {
+        let mut left = SpaceSaving::new(4);
+        let mut right = SpaceSaving::new(4);
+        left.add("a", 2);
+        left.add("b", 1);
+        right.add("a", 1);
+        right.add("c", 5);
+        left.merge(right);
+
+        let counts = left.counts();
+        assert_eq!((counts["a"], counts["b"], counts["c"]), (3, 1, 5));
+        assert_eq!(left.error("c"), 0);
+        assert_eq!(left.total(), 9);
+    }
+}
@@ -0,0 +1,22 @@
+use std::env;
+use std::process::{Command, Output};
+
+/// Runs wordfreq with `args`, without a config file and with only the
+/// WORDFREQ_ variables in `defaults`.
+pub fn wordfreq(defaults: &[(&str, &str)], args: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_wordfreq"));
+    for (name, _) in env::vars_os() {
+        if name.to_string_lossy().starts_with("WORDFREQ_") {
+            command.env_remove(name);
+        }
+    }
+    command
+        .env(
+            "XDG_CONFIG_HOME",
+            env::temp_dir().join("wordfreq-no-config"),
+        )
+        .envs(defaults.iter().copied())
+        .args(args)
+        .output()
+        .unwrap()
+}
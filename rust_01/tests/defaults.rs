@@ -0,0 +1,42 @@
+mod common;
+
+use std::process::Output;
+
+use common::wordfreq;
+
+fn run(defaults: &[(&str, &str)], text: &str) -> Output {
+    wordfreq(defaults, &["--text", text])
+}
+
+#[test]
+fn measure_defaults_do_not_turn_on_their_modes() {
+    let text = "the cat sat on the mat with the hat";
+    let plain = run(&[], text);
+    assert!(plain.status.success());
+    assert!(String::from_utf8_lossy(&plain.stdout).starts_with("Word frequency:"));
+
+    let defaults = [
+        ("WORDFREQ_COMPARE_BY", "ll"),
+        ("WORDFREQ_CODE_LANGUAGE", "rust"),
+        ("WORDFREQ_COLLOCATIONS_BY", "pmi"),
+    ];
+    for default in defaults {
+        let output = run(&[default], text);
+        assert!(output.status.success(), "{}", default.0);
+        assert_eq!(output.stdout, plain.stdout, "{}", default.0);
+    }
+}
+
+#[test]
+fn measure_defaults_apply_when_their_mode_is_on() {
+    let text = "new york is in new york state near new york city";
+    let collocations = run(
+        &[
+            ("WORDFREQ_COLLOCATIONS", "1"),
+            ("WORDFREQ_COLLOCATIONS_BY", "pmi"),
+        ],
+        text,
+    );
+    assert!(collocations.status.success());
+    assert!(String::from_utf8_lossy(&collocations.stdout).starts_with("Collocations by PMI:"));
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use common::wordfreq;
+
+const TEXT: &str = "The cat sat. The dog ran.";
+
+#[test]
+fn csv_stats_follow_the_words_on_stdout() {
+    let output = wordfreq(&[], &["--stats", "--format", "csv", "--text", TEXT]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let (words, stats) = stdout.split_once("\n\n").unwrap();
+    assert!(words.starts_with("rank,word,count,frequency\n1,The,2,"));
+    let stats: Vec<&str> = stats.lines().collect();
+    assert_eq!(
+        stats[..3],
+        ["statistic,value", "total_tokens,6", "distinct_words,5"]
+    );
+    assert!(stats.contains(&"sentences,2"));
+    assert!(stats.iter().any(|row| row.starts_with("type_token_ratio,")));
+}
+
+#[test]
+fn tsv_stats_are_labeled_by_input() {
+    let output = wordfreq(
+        &[],
+        &[
+            "--stats",
+            "--format",
+            "tsv",
+            "--no-header",
+            "--text",
+            TEXT,
+            "a b",
+        ],
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let (_, stats) = stdout.split_once("\n\n").unwrap();
+    let totals: Vec<&str> = stats
+        .lines()
+        .filter(|row| row.contains("\ttotal_tokens\t"))
+        .collect();
+    assert_eq!(
+        totals,
+        [
+            "<text>\ttotal_tokens\t6",
+            "<text>\ttotal_tokens\t2",
+            "<total>\ttotal_tokens\t8"
+        ]
+    );
+}
+
+#[test]
+fn csv_metadata_goes_to_stderr() {
+    let output = wordfreq(&[], &["--format", "csv", "--text", TEXT, "a b"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.lines().all(|row| !row.starts_with('#')));
+    assert!(stdout.starts_with("file,rank,word,count,frequency\n"));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(
+        stderr.lines().collect::<Vec<_>>(),
+        [
+            "# file: <text>, total_tokens: 6, distinct_words: 5",
+            "# file: <text>, total_tokens: 2, distinct_words: 2",
+            "# file: <total>, total_tokens: 8, distinct_words: 7",
+        ]
+    );
+
+    let output = wordfreq(&[], &["--format", "csv", "--no-header", "--text", TEXT]);
+    assert!(output.stderr.is_empty());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("1,The,2,"));
+}